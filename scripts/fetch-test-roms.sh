@@ -0,0 +1,16 @@
+#!/bin/sh
+# Downloads the community test ROMs checked by the conformance tests into
+# tests/fixtures, from a pinned release of Timendus' chip8-test-suite.
+#
+# usage: scripts/fetch-test-roms.sh [<fixtures directory>]
+
+set -eu
+
+TAG="${CHIP8_TEST_SUITE_TAG:-v4.1}"
+URL="https://raw.githubusercontent.com/Timendus/chip8-test-suite/$TAG/bin"
+DIR="${1:-$(dirname "$0")/../tests/fixtures}"
+
+for rom in 3-corax+.ch8 4-flags.ch8 5-quirks.ch8 6-keypad.ch8; do
+    echo "fetching $rom ($TAG)"
+    curl --fail --silent --show-error --location --output "$DIR/$rom" "$URL/$rom"
+done
@@ -0,0 +1,45 @@
+pub const PATTERN_SIZE: usize = 0x10;
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// 1-bit samples played while the sound timer is active, most significant
+/// bit first.
+pub type Pattern = [u8; PATTERN_SIZE];
+
+/// Square wave played until a program loads its own pattern with `AUDIO`.
+pub const DEFAULT_PATTERN: Pattern = [0xF0; PATTERN_SIZE];
+
+/// Number of pattern bits played per second for the XO-CHIP `pitch` register.
+pub fn playback_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+/// Value of the bit at `position` in `pattern`, wrapping around its 128 bits.
+pub fn sample(pattern: &Pattern, position: usize) -> bool {
+    let position = position % (PATTERN_SIZE * 8);
+
+    (pattern[position / 8] << (position % 8) >> 7) != 0
+}
+
+#[cfg(test)]
+mod audio_test {
+    use super::*;
+
+    #[test]
+    fn test_playback_rate() {
+        assert_eq!(playback_rate(DEFAULT_PITCH), 4000.0);
+        assert!((playback_rate(DEFAULT_PITCH + 48) - 8000.0).abs() < 0.01);
+        assert!((playback_rate(DEFAULT_PITCH - 48) - 2000.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut pattern: Pattern = [0; PATTERN_SIZE];
+        pattern[0] = 0x80;
+        pattern[1] = 0x01;
+
+        assert!(sample(&pattern, 0));
+        assert!(!sample(&pattern, 1));
+        assert!(sample(&pattern, 15));
+        assert!(sample(&pattern, PATTERN_SIZE * 8));
+    }
+}
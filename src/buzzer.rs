@@ -1,31 +1,40 @@
 use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
 
 use crate::context::Context;
+use crust::audio::{playback_rate, sample, Pattern, DEFAULT_PATTERN, DEFAULT_PITCH, PATTERN_SIZE};
 
-struct SquareWave {
+struct PatternWave {
+    pattern: Pattern,
+    freq: f32,
     phase_inc: f32,
     phase: f32,
     volume: f32,
 }
 
-impl AudioCallback for SquareWave {
+impl PatternWave {
+    fn set_pitch(&mut self, pitch: u8) {
+        self.phase_inc = playback_rate(pitch) / self.freq;
+    }
+}
+
+impl AudioCallback for PatternWave {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
         for x in out.iter_mut() {
-            *x = if self.phase >= 0.0 && self.phase <= 0.5 {
+            *x = if sample(&self.pattern, self.phase as usize) {
                 self.volume
             } else {
                 -self.volume
             };
 
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+            self.phase = (self.phase + self.phase_inc) % (PATTERN_SIZE * 8) as f32;
         }
     }
 }
 
 pub struct Buzzer {
-    device: AudioDevice<SquareWave>,
+    device: AudioDevice<PatternWave>,
 }
 
 impl Buzzer {
@@ -38,14 +47,28 @@ impl Buzzer {
         };
 
         let device = audio_subsystem
-            .open_playback(None, &desired_spec, |spec| SquareWave {
-                phase_inc: 440.0 / spec.freq as f32,
-                phase: 0.0,
-                volume: 0.10,
+            .open_playback(None, &desired_spec, |spec| {
+                let mut wave = PatternWave {
+                    pattern: DEFAULT_PATTERN,
+                    freq: spec.freq as f32,
+                    phase_inc: 0.0,
+                    phase: 0.0,
+                    volume: 0.10,
+                };
+                wave.set_pitch(DEFAULT_PITCH);
+                wave
             })
             .unwrap();
 
-        Self { device: device }
+        Self { device }
+    }
+
+    /// Plays `pattern` at the rate selected by the XO-CHIP `pitch` register.
+    pub fn set_pattern(&mut self, pattern: &Pattern, pitch: u8) {
+        let mut wave = self.device.lock();
+
+        wave.pattern = *pattern;
+        wave.set_pitch(pitch);
     }
 
     pub fn play(&mut self) {
@@ -0,0 +1,93 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Source of time for the timing code.
+///
+/// Times are durations since an origin chosen by the clock.
+pub trait Clock {
+    fn now(&self) -> Duration;
+
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// Wall clock time, sleeping the current thread.
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// Clock that only moves when told to. Sleeping returns immediately after
+/// moving the clock forward.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VirtualClock {
+    now: Duration,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Duration::from_secs(0),
+        }
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        self.now += duration
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[cfg(test)]
+mod clock_test {
+    use super::*;
+
+    #[test]
+    fn test_system_clock() {
+        let mut clock = SystemClock::new();
+        let before = clock.now();
+
+        clock.sleep(Duration::from_millis(1));
+        assert!(clock.now() >= before + Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_virtual_clock() {
+        let mut clock = VirtualClock::new();
+        assert_eq!(clock.now(), Duration::from_secs(0));
+
+        clock.sleep(Duration::from_millis(16));
+        clock.advance(Duration::from_nanos(5));
+        assert_eq!(clock.now(), Duration::from_nanos(16_000_005));
+    }
+}
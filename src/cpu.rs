@@ -1,18 +1,24 @@
-use rand;
-
-use crate::font::FONT;
-use crate::keyboard::State;
-use crust::rom::{Reader, RomBuffer};
-
-use crust::HEIGHT;
-use crust::MEMORY_SIZE;
-use crust::WIDTH;
-
-const INSTRUCTION_LENGTH: u16 = 2;
-const GENERAL_PURPOSE_REGISTERS: usize = 0x10;
-const STACK_SIZE: usize = 0x10;
-
-pub type VRAM = [[bool; WIDTH]; HEIGHT];
+use crate::audio::{Pattern, DEFAULT_PATTERN, DEFAULT_PITCH, PATTERN_SIZE};
+use crate::decoder::{Args, Instruction, Opcode};
+use crate::fault::{CpuFault, FaultAction, FaultPolicy};
+use crate::font::{BIG_FONT, BIG_FONT_START, FONT};
+use crate::keypad::State;
+use crate::quirks::{IndexIncrement, Profile, Quirks};
+use crate::random::Random;
+use crate::rom::Reader;
+use crate::snapshot::Snapshot;
+use crate::trace::Trace;
+use crate::vram::Resolution;
+use crate::watch::{Access, Watchpoints};
+
+use crate::{CHIP8_MEMORY_SIZE, MEMORY_SIZE};
+
+pub const INSTRUCTION_LENGTH: u16 = 2;
+pub const GENERAL_PURPOSE_REGISTERS: usize = 0x10;
+pub const STACK_SIZE: usize = 0x10;
+pub const FLAG_REGISTERS: usize = 0x10;
+
+pub use crate::vram::VRAM;
 
 pub struct CPU {
     i: usize,
@@ -25,7 +31,37 @@ pub struct CPU {
 
     stack: [u16; STACK_SIZE],
     ram: [u8; MEMORY_SIZE],
+    // Addressable part of the RAM, and bytes of it loaded from the ROM.
+    memory_size: usize,
+    rom_size: usize,
     vram: VRAM,
+    flags: [u8; FLAG_REGISTERS],
+    halted: bool,
+
+    plane: u8,
+    pattern: Pattern,
+    pitch: u8,
+
+    quirks: Quirks,
+    vblank: bool,
+    random: Random,
+
+    fault_policy: FaultPolicy,
+    fault: Option<CpuFault>,
+    // First fault skipped by the policy, until taken.
+    ignored_fault: Option<CpuFault>,
+    ignored_faults: u64,
+    // Address of the instruction being executed, reported by faults.
+    instruction_pc: u16,
+    cycles: u64,
+    trace: Option<Trace>,
+    watchpoints: Watchpoints,
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPU {
@@ -39,16 +75,34 @@ impl CPU {
             st: 0,
             stack: [0; STACK_SIZE],
             ram: [0; MEMORY_SIZE],
-            vram: [[false; WIDTH]; HEIGHT],
+            memory_size: CHIP8_MEMORY_SIZE,
+            rom_size: 0,
+            vram: VRAM::default(),
+            flags: [0; FLAG_REGISTERS],
+            halted: false,
+            plane: 0x1,
+            pattern: DEFAULT_PATTERN,
+            pitch: DEFAULT_PITCH,
+            quirks: Quirks::default(),
+            vblank: true,
+            random: Random::default(),
+            fault_policy: FaultPolicy::default(),
+            fault: None,
+            ignored_fault: None,
+            ignored_faults: 0,
+            instruction_pc: 0,
+            cycles: 0,
+            trace: None,
+            watchpoints: Watchpoints::new(),
         }
     }
 
-    pub fn init(rom: &RomBuffer) -> CPU {
+    pub fn init(rom: &[u8]) -> CPU {
         let mut cpu = CPU::new();
         cpu.pc = 0x200;
 
         cpu.load_font();
-        cpu.load_rom(&rom);
+        cpu.load_rom(rom);
 
         cpu
     }
@@ -57,12 +111,16 @@ impl CPU {
         for (i, &elem) in FONT.iter().enumerate() {
             self.ram[i] = elem
         }
+        for (i, &elem) in BIG_FONT.iter().enumerate() {
+            self.ram[BIG_FONT_START + i] = elem
+        }
     }
 
-    fn load_rom(&mut self, rom: &RomBuffer) {
+    fn load_rom(&mut self, rom: &[u8]) {
         let reader = Reader::new(rom);
         for (i, elem) in reader.enumerate() {
-            self.ram[self.pc as usize + i] = elem
+            self.ram[self.pc as usize + i] = elem;
+            self.rom_size = i + 1;
         }
     }
 
@@ -70,6 +128,248 @@ impl CPU {
         &self.vram
     }
 
+    /// Whether the program stopped itself with `EXIT`.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn flags(&self) -> &[u8; FLAG_REGISTERS] {
+        &self.flags
+    }
+
+    /// Bitplanes affected by drawing, clearing and scrolling.
+    pub fn plane(&self) -> u8 {
+        self.plane
+    }
+
+    pub fn audio_pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    pub fn i(&self) -> usize {
+        self.i
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn sp(&self) -> usize {
+        self.sp
+    }
+
+    pub fn v(&self) -> &[u8; GENERAL_PURPOSE_REGISTERS] {
+        &self.v
+    }
+
+    pub fn dt(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn st(&self) -> u8 {
+        self.st
+    }
+
+    pub fn stack(&self) -> &[u16; STACK_SIZE] {
+        &self.stack
+    }
+
+    /// Memory addressable by the program.
+    pub fn ram(&self) -> &[u8] {
+        &self.ram[..self.memory_size]
+    }
+
+    /// Bytes of addressable memory: 4 KiB, or 64 KiB for XO-CHIP.
+    pub fn memory_size(&self) -> usize {
+        self.memory_size
+    }
+
+    /// Limits the addressable memory to `memory_size` bytes, failing when
+    /// the loaded ROM doesn't fit.
+    pub fn set_memory_size(&mut self, memory_size: usize) -> Result<(), String> {
+        assert!(memory_size <= MEMORY_SIZE, "memory size out of range");
+        if 0x200 + self.rom_size > memory_size {
+            return Err(format!(
+                "ROM of {} bytes doesn't fit in {} bytes of memory.",
+                self.rom_size, memory_size
+            ));
+        }
+
+        self.memory_size = memory_size;
+        Ok(())
+    }
+
+    /// Emulates the machine of `profile`, failing when the loaded ROM
+    /// doesn't fit in its memory.
+    pub fn set_profile(&mut self, profile: &Profile) -> Result<(), String> {
+        self.set_memory_size(profile.memory_size)?;
+        self.quirks = profile.quirks;
+        Ok(())
+    }
+
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        self.v[x] = value
+    }
+
+    pub fn set_i(&mut self, i: usize) {
+        self.i = i
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc
+    }
+
+    /// Sets the stack pointer, panicking past the end of the stack.
+    pub fn set_sp(&mut self, sp: usize) {
+        assert!(sp <= STACK_SIZE, "stack pointer out of range");
+        self.sp = sp
+    }
+
+    pub fn set_dt(&mut self, dt: u8) {
+        self.dt = dt
+    }
+
+    pub fn set_st(&mut self, st: u8) {
+        self.st = st
+    }
+
+    /// Writes `bytes` to memory from `address`, panicking past the end of
+    /// the RAM.
+    pub fn poke(&mut self, address: usize, bytes: &[u8]) {
+        self.ram[address..address + bytes.len()].copy_from_slice(bytes)
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks
+    }
+
+    /// Reseeds the generator used by `RND`, making the run reproducible.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed)
+    }
+
+    pub fn fault_policy(&self) -> &FaultPolicy {
+        &self.fault_policy
+    }
+
+    pub fn set_fault_policy(&mut self, fault_policy: FaultPolicy) {
+        self.fault_policy = fault_policy
+    }
+
+    /// Number of instructions executed.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Starts or stops tracing the executed instructions.
+    pub fn set_trace(&mut self, trace: Option<Trace>) {
+        self.trace = trace
+    }
+
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Watchpoints checked by `tick()`, recording the watched accesses.
+    pub fn watchpoints(&self) -> &Watchpoints {
+        &self.watchpoints
+    }
+
+    pub fn watchpoints_mut(&mut self) -> &mut Watchpoints {
+        &mut self.watchpoints
+    }
+
+    /// Fault that halted the CPU, if any.
+    pub fn fault(&self) -> Option<CpuFault> {
+        self.fault
+    }
+
+    /// First fault skipped by the fault policy, returned once so that a
+    /// frontend can report it without flooding its output.
+    pub fn take_ignored_fault(&mut self) -> Option<CpuFault> {
+        self.ignored_fault.take()
+    }
+
+    /// Number of faults skipped by the fault policy.
+    pub fn ignored_faults(&self) -> u64 {
+        self.ignored_faults
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            i: self.i,
+            pc: self.pc,
+            sp: self.sp,
+            v: self.v,
+            dt: self.dt,
+            st: self.st,
+            stack: self.stack,
+            ram: self.ram.to_vec(),
+            vram: self.vram.clone(),
+            flags: self.flags,
+            halted: self.halted,
+            plane: self.plane,
+            pattern: self.pattern,
+            pitch: self.pitch,
+            vblank: self.vblank,
+            random: self.random.state(),
+            instruction_pc: self.instruction_pc,
+            cycles: self.cycles,
+        }
+    }
+
+    /// Puts the CPU back in the state of `snapshot`, clearing any fault.
+    /// Quirks and the fault policy are kept.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.i = snapshot.i;
+        self.pc = snapshot.pc;
+        self.sp = snapshot.sp;
+        self.v = snapshot.v;
+        self.dt = snapshot.dt;
+        self.st = snapshot.st;
+        self.stack = snapshot.stack;
+        self.ram.copy_from_slice(&snapshot.ram);
+        self.vram = snapshot.vram.clone();
+        self.flags = snapshot.flags;
+        self.halted = snapshot.halted;
+        self.plane = snapshot.plane;
+        self.pattern = snapshot.pattern;
+        self.pitch = snapshot.pitch;
+        self.vblank = snapshot.vblank;
+        self.random = Random::new(snapshot.random);
+        self.instruction_pc = snapshot.instruction_pc;
+        self.cycles = snapshot.cycles;
+        self.fault = None;
+    }
+
+    /// Restarts the program at 0x200 with cleared registers, stack and
+    /// timers, clearing any fault. Memory and the screen are kept.
+    pub fn soft_reset(&mut self) {
+        self.i = 0;
+        self.pc = 0x200;
+        self.sp = 0;
+        self.v = [0; GENERAL_PURPOSE_REGISTERS];
+        self.dt = 0;
+        self.st = 0;
+        self.stack = [0; STACK_SIZE];
+        self.halted = false;
+        self.fault = None;
+    }
+
+    /// Signals the start of a frame, releasing a `DRW` held by the
+    /// display wait quirk.
+    pub fn vblank(&mut self) {
+        self.vblank = true
+    }
+
     pub fn beeping(&self) -> bool {
         self.st > 0
     }
@@ -86,258 +386,519 @@ impl CPU {
         }
     }
 
-    pub fn tick(&mut self, input: &State) {
+    /// Runs one frame: `instructions` instructions followed by a single
+    /// update of the timers.
+    ///
+    /// Stops early when the program exits or a fault halts the CPU.
+    pub fn run_frame(&mut self, input: &State, instructions: usize) -> Result<(), CpuFault> {
+        self.vblank();
+
+        for _ in 0..instructions {
+            if self.halted {
+                break;
+            }
+            self.tick(input)?;
+        }
+
+        self.decrement_delay();
+        self.decrement_sound();
+
+        Ok(())
+    }
+
+    /// Executes the instruction at PC.
+    ///
+    /// Faults whose action is `Halt` leave PC on the faulting instruction and
+    /// are returned by every following call.
+    pub fn tick(&mut self, input: &State) -> Result<(), CpuFault> {
+        if let Some(fault) = self.fault {
+            return Err(fault);
+        }
+
+        if self.halted {
+            return Ok(());
+        }
+
         let instruction = self.fetch_instruction();
 
-        let nibbles = (
-            (instruction & 0xF000) >> 12,
-            (instruction & 0x0F00) >> 8,
-            (instruction & 0x00F0) >> 4,
-            (instruction & 0x000F),
-        );
+        if !self.waits(&instruction, input) {
+            if let Some(mut trace) = self.trace.take() {
+                trace.record(self, &instruction);
+                self.trace = Some(trace);
+            }
+        }
 
-        let x: usize = nibbles.1 as usize;
-        let y: usize = nibbles.2 as usize;
-        let n: usize = nibbles.3 as usize;
+        self.instruction_pc = self.pc;
+        for offset in 0..instruction.length() {
+            let address = self.pc.wrapping_add(offset) as usize % self.memory_size();
+            let value = self.ram[address];
+            self.watchpoints
+                .check(Access::Execute, address, value, self.pc);
+        }
+        self.cycles += 1;
+        self.pc = self.pc.wrapping_add(instruction.length());
+
+        if let Err(fault) = self.execute(&instruction, input) {
+            self.pc = self.instruction_pc;
+            self.fault = Some(fault);
+
+            return Err(fault);
+        }
+
+        Ok(())
+    }
 
-        let kk: u8 = (instruction & 0x00FF) as u8;
-        let nnn: u16 = instruction & 0x0FFF;
+    /// Whether `instruction` waits for a key or the next frame, leaving PC on
+    /// it instead of running.
+    fn waits(&self, instruction: &Instruction, input: &State) -> bool {
+        match instruction.opcode {
+            Opcode::LD_REG_KEY => input.as_raw() == 0,
+            Opcode::DRW_REG_REG_CONST => self.quirks.display_wait && !self.vblank,
+            _ => false,
+        }
+    }
+
+    fn execute(&mut self, instruction: &Instruction, input: &State) -> Result<(), CpuFault> {
+        let Args {
+            x, y, n, kk, nnn, ..
+        } = instruction.args;
 
-        self.pc += INSTRUCTION_LENGTH;
+        use Opcode::*;
 
-        match nibbles {
+        match instruction.opcode {
+            // SCD nibble
+            SCD_CONST => {
+                self.vram.scroll_down(n, self.plane);
+            }
             // CLS
-            (0x0, 0x0, 0xE, 0x0) => {
-                self.vram = [[false; WIDTH]; HEIGHT];
+            CLS => {
+                self.vram.clear(self.plane);
             }
             // RET
-            (0x0, 0x0, 0xE, 0xE) => {
+            RET => {
+                if self.sp == 0 {
+                    match self.raise(CpuFault::StackUnderflow {
+                        pc: self.instruction_pc,
+                    })? {
+                        FaultAction::Wrap => self.sp = STACK_SIZE,
+                        _ => return Ok(()),
+                    }
+                }
+
                 self.sp -= 1;
                 self.pc = self.stack[self.sp];
             }
+            // SCR
+            SCR => {
+                self.vram.scroll_right(4, self.plane);
+            }
+            // SCL
+            SCL => {
+                self.vram.scroll_left(4, self.plane);
+            }
+            // EXIT
+            EXIT => {
+                self.pc = self.instruction_pc;
+                self.halted = true;
+            }
+            // LOW
+            LOW => {
+                self.vram.set_resolution(Resolution::Low);
+            }
+            // HIGH
+            HIGH => {
+                self.vram.set_resolution(Resolution::High);
+            }
             // JP addr
-            (0x1, _, _, _) => {
+            JP_ADDR => {
                 self.pc = nnn;
             }
             // CALL addr
-            (0x2, _, _, _) => {
+            CALL_ADDR => {
+                if self.sp >= STACK_SIZE {
+                    match self.raise(CpuFault::StackOverflow {
+                        pc: self.instruction_pc,
+                    })? {
+                        FaultAction::Wrap => self.sp = 0,
+                        _ => return Ok(()),
+                    }
+                }
+
                 self.stack[self.sp] = self.pc;
                 self.sp += 1;
                 self.pc = nnn;
             }
             // SE Vx, byte
-            (0x3, _, _, _) => {
+            SE_REG_CONST => {
                 if self.v[x] == kk {
-                    self.pc += INSTRUCTION_LENGTH;
+                    self.skip();
                 }
             }
             // SNE Vx, byte
-            (0x4, _, _, _) => {
+            SNE_REG_CONST => {
                 if self.v[x] != kk {
-                    self.pc += INSTRUCTION_LENGTH;
+                    self.skip();
                 }
             }
             // SE Vx, Vy
-            (0x5, _, _, 0x0) => {
+            SE_REG_REG => {
                 if self.v[x] == self.v[y] {
-                    self.pc += INSTRUCTION_LENGTH;
+                    self.skip();
+                }
+            }
+            // SAVE Vx, Vy
+            SAVE_REG_REG => {
+                for (offset, idx) in Self::register_range(x, y).enumerate() {
+                    self.write(self.i + offset, self.v[idx])?;
+                }
+            }
+            // LOAD Vx, Vy
+            LOAD_REG_REG => {
+                for (offset, idx) in Self::register_range(x, y).enumerate() {
+                    self.v[idx] = self.read(self.i + offset)?;
                 }
             }
             // LD Vx, byte
-            (0x6, _, _, _) => {
+            LD_REG_CONST => {
                 self.v[x] = kk;
             }
             // ADD Vx, byte
-            (0x7, _, _, _) => {
+            ADD_REG_CONST => {
                 let vx = self.v[x] as u16;
                 let val = kk as u16;
                 let result = vx + val;
                 self.v[x] = (result & 0xff) as u8;
             }
             // LD Vx, Vy
-            (0x8, _, _, 0x0) => {
+            LD_REG_REG => {
                 self.v[x] = self.v[y];
             }
             // OR Vx, Vy
-            (0x8, _, _, 0x1) => {
+            OR_REG_REG => {
                 self.v[x] |= self.v[y];
+                self.reset_flag();
             }
             // AND Vx, Vy
-            (0x8, _, _, 0x2) => {
+            AND_REG_REG => {
                 self.v[x] &= self.v[y];
+                self.reset_flag();
             }
             // XOR Vx, Vy
-            (0x8, _, _, 0x3) => {
+            XOR_REG_REG => {
                 self.v[x] ^= self.v[y];
+                self.reset_flag();
             }
             // ADD Vx, Vy
-            (0x8, _, _, 0x4) => {
+            // The flag is written last, so it wins when x is 0xF.
+            ADD_REG_REG => {
                 let (result, overflowed) = self.v[x].overflowing_add(self.v[y]);
 
-                self.v[0xf] = if overflowed { 1 } else { 0 };
-
                 self.v[x] = result;
+                self.v[0xf] = if overflowed { 1 } else { 0 };
             }
             // SUB Vx, Vy
-            (0x8, _, _, 0x5) => {
-                let (result, overflowed) = self.v[x].overflowing_sub(self.v[y]);
-
-                self.v[0xf] = if overflowed { 1 } else { 0 };
+            // VF is set when there is no borrow.
+            SUB_REG_REG => {
+                let (result, borrowed) = self.v[x].overflowing_sub(self.v[y]);
 
                 self.v[x] = result;
+                self.v[0xf] = if borrowed { 0 } else { 1 };
             }
             // SHR Vx {, Vy}
-            (0x8, _, _, 0x6) => {
-                self.v[0xf] = self.v[x] & 0x1;
-                self.v[x] >>= 1;
+            SHR_REG => {
+                let source = self.shift_source(x, y);
+
+                self.v[x] = source >> 1;
+                self.v[0xf] = source & 0x1;
             }
             // SUBN Vx, Vy
-            (0x8, _, _, 0x7) => {
-                let (result, overflowed) = self.v[y].overflowing_sub(self.v[x]);
-
-                self.v[0xf] = if overflowed { 1 } else { 0 };
+            SUBN_REG_REG => {
+                let (result, borrowed) = self.v[y].overflowing_sub(self.v[x]);
 
                 self.v[x] = result;
+                self.v[0xf] = if borrowed { 0 } else { 1 };
             }
             // SHL Vx {, Vy}
-            (0x8, _, _, 0xE) => {
-                self.v[0xf] = self.v[x] & 0x1;
-                self.v[x] <<= 1;
+            SHL_REG => {
+                let source = self.shift_source(x, y);
+
+                self.v[x] = source << 1;
+                self.v[0xf] = source >> 7;
             }
             // SNE Vx, Vy
-            (0x9, _, _, 0x00) => {
+            SNE_REG_REG => {
                 if self.v[x] != self.v[y] {
-                    self.pc += INSTRUCTION_LENGTH;
+                    self.skip();
                 }
             }
             // LD I, addr
-            (0xA, _, _, _) => {
+            LD_I_ADDR => {
                 self.i = nnn as usize;
             }
+            // LD I, long
+            LD_I_LONG => {
+                self.i = instruction.args.nnnn as usize;
+            }
             // JP V0, addr
-            (0xB, _, _, _) => {
-                self.pc = nnn + (self.v[0] as u16);
+            JP_V0_ADDR => {
+                let offset = if self.quirks.jump_vx {
+                    self.v[x]
+                } else {
+                    self.v[0]
+                };
+
+                self.pc = nnn + (offset as u16);
             }
             // RND Vx, byte
-            (0xC, _, _, _) => {
-                self.v[x] = rand::random::<u8>() & kk;
+            RND_REG_CONST => {
+                self.v[x] = self.random.next_u8() & kk;
             }
             // DRW Vx, Vy, nibble
-            (0xD, _, _, _) => {
-                self.v[0xf] = 0;
-                for idx in 0..n {
-                    for bit in 0..8 {
-                        let posx = (self.v[x] as usize + bit) % WIDTH;
-                        let posy = (self.v[y] as usize + idx) % HEIGHT;
-                        let cell = &mut self.vram[posy][posx];
+            DRW_REG_REG_CONST => {
+                if self.quirks.display_wait {
+                    if !self.vblank {
+                        self.pc = self.instruction_pc;
+                        return Ok(());
+                    }
+                    self.vblank = false;
+                }
 
-                        let color = (self.ram[(self.i + idx) as usize] << bit >> 7) != 0;
+                // DXY0 draws a 16x16 sprite, two bytes per row.
+                let (rows, row_length) = if n == 0 { (16, 2) } else { (n, 1) };
+                let width = self.vram.width();
+                let height = self.vram.height();
+                let originx = self.v[x] as usize % width;
+                let originy = self.v[y] as usize % height;
+                let selected = self.plane;
+                let mut address = self.i;
 
-                        if *cell && color {
-                            self.v[0xf] = 1;
+                self.v[0xf] = 0;
+                // Each selected plane reads its own sprite, one after the other.
+                for plane in [0x1, 0x2].iter().filter(|&&plane| selected & plane != 0) {
+                    for idx in 0..rows {
+                        for bit in 0..row_length * 8 {
+                            let posx = originx + bit;
+                            let posy = originy + idx;
+
+                            if self.quirks.clip_sprites && (posx >= width || posy >= height) {
+                                continue;
+                            }
+
+                            let byte = self.read(address + idx * row_length + bit / 8)?;
+                            if (byte << (bit % 8) >> 7) == 0 {
+                                continue;
+                            }
+
+                            if self.vram.toggle(posx % width, posy % height, *plane) {
+                                self.v[0xf] = 1;
+                            }
                         }
-
-                        *cell ^= color;
                     }
+                    address += rows * row_length;
                 }
             }
             // SKP Vx
-            (0xE, _, 0x9, 0xE) => {
-                if input.has_key(self.v[x]) {
-                    self.pc += INSTRUCTION_LENGTH;
+            SKP_REG => {
+                if input.has_key(self.v[x] & 0xF) {
+                    self.skip();
                 }
             }
             // SKNP Vx
-            (0xE, _, 0xA, 0x1) => {
-                if !input.has_key(self.v[x]) {
-                    self.pc += INSTRUCTION_LENGTH;
+            SKNP_REG => {
+                if !input.has_key(self.v[x] & 0xF) {
+                    self.skip();
                 }
             }
             // LD Vx, DT
-            (0xF, _, 0x0, 0x7) => {
+            LD_REG_DELAY => {
                 self.v[x] = self.dt;
             }
             // LD Vx, K
-            (0xF, _, 0x0, 0xA) => {
-                self.pc -= INSTRUCTION_LENGTH;
-                let raw_input = input.as_raw();
-
+            LD_REG_KEY => {
                 for bit in 0x0..0x10 {
-                    if (raw_input << bit >> 7) == 1 {
-                        self.pc += INSTRUCTION_LENGTH;
+                    if input.has_key(bit) {
                         self.v[x] = bit;
-                        return;
+                        return Ok(());
                     }
                 }
+
+                self.pc = self.instruction_pc;
             }
             // LD DT, Vx
-            (0xF, _, 0x1, 0x5) => {
+            LD_DELAY_REG => {
                 self.dt = self.v[x];
             }
             // LD ST, Vx
-            (0xF, _, 0x1, 0x8) => {
+            LD_SOUND_REG => {
                 self.st = self.v[x];
             }
             // ADD I, Vx
-            (0xF, _, 0x1, 0xE) => {
+            ADD_I_REG => {
                 self.i += self.v[x] as usize;
             }
             // LD F, Vx
-            (0xF, _, 0x2, 0x9) => {
+            LD_FONT_CHAR => {
                 self.i = self.v[x] as usize * 5;
             }
+            // LD HF, Vx
+            LD_BIG_FONT_CHAR => {
+                self.i = BIG_FONT_START + (self.v[x] & 0xF) as usize * 10;
+            }
             // LD B, Vx
-            (0xF, _, 0x3, 0x3) => {
-                self.ram[self.i] = self.v[x] / 100;
-                self.ram[self.i + 1] = (self.v[x] % 100) / 10;
-                self.ram[self.i + 2] = self.v[x] % 10;
+            LD_BCD => {
+                self.write(self.i, self.v[x] / 100)?;
+                self.write(self.i + 1, (self.v[x] % 100) / 10)?;
+                self.write(self.i + 2, self.v[x] % 10)?;
             }
             // LD [I], Vx
-            (0xF, _, 0x5, 0x5) => {
+            LD_IND_REG => {
                 for idx in 0x0..x + 1 {
-                    self.ram[self.i + idx] = self.v[idx];
+                    self.write(self.i + idx, self.v[idx])?;
                 }
+                self.increment_index(x);
             }
             // LD Vx, [I]
-            (0xF, _, 0x6, 0x5) => {
+            LD_REG_IND => {
                 for idx in 0x0..x + 1 {
-                    self.v[idx] = self.ram[self.i + idx];
+                    self.v[idx] = self.read(self.i + idx)?;
                 }
+                self.increment_index(x);
+            }
+            // LD R, Vx
+            LD_FLAGS_REG => {
+                self.flags[..x + 1].copy_from_slice(&self.v[..x + 1]);
             }
-            (_, _, _, _) => {
-                println!("Unknown instruction 0x{:x}", instruction);
+            // LD Vx, R
+            LD_REG_FLAGS => {
+                self.v[..x + 1].copy_from_slice(&self.flags[..x + 1]);
+            }
+            // PLANE n
+            PLANE_CONST => {
+                self.plane = x as u8 & 0x3;
+            }
+            // AUDIO
+            AUDIO => {
+                for idx in 0..PATTERN_SIZE {
+                    self.pattern[idx] = self.read(self.i + idx)?;
+                }
+            }
+            // PITCH Vx
+            PITCH_REG => {
+                self.pitch = self.v[x];
+            }
+            UNKNOWN => {
+                self.raise(CpuFault::UnknownOpcode {
+                    pc: self.instruction_pc,
+                    opcode: instruction.raw,
+                })?;
             }
         }
+
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    fn print_state(&self, instruction: u16) {
-        println!("Instruction: 0x{:x}", instruction);
-        for x in 0..0x10 {
-            print!("V{:X} = 0x{:x}\n", x, self.v[x]);
+    /// Returns the action of the policy for `fault`, or the fault itself
+    /// when the CPU must halt.
+    fn raise(&mut self, fault: CpuFault) -> Result<FaultAction, CpuFault> {
+        match self.fault_policy.action(&fault) {
+            FaultAction::Halt => Err(fault),
+            FaultAction::Ignore => {
+                if self.ignored_faults == 0 {
+                    self.ignored_fault = Some(fault);
+                }
+                self.ignored_faults += 1;
+                Ok(FaultAction::Ignore)
+            }
+            action => Ok(action),
         }
-        println!(
-            "i = 0x{:04x}\npc = 0x{:04x}\nsp = 0x{:x}\ndt = 0x{:x}\nst = 0x{:x}\n",
-            self.i, self.pc, self.sp, self.dt, self.st
-        );
     }
 
-    #[allow(dead_code)]
-    fn print_memory(&self, from: usize, length: usize) {
-        for x in from..from + length {
-            println!("{:04x}: {:02x}", x, self.ram[x]);
+    /// Resolves a RAM address, or `None` when an out of bounds access is
+    /// ignored.
+    fn address(&mut self, address: usize) -> Result<Option<usize>, CpuFault> {
+        if address < self.memory_size() {
+            return Ok(Some(address));
+        }
+
+        let fault = CpuFault::OutOfBounds {
+            pc: self.instruction_pc,
+            address,
+        };
+
+        match self.raise(fault)? {
+            FaultAction::Wrap => Ok(Some(address % self.memory_size())),
+            _ => Ok(None),
+        }
+    }
+
+    fn read(&mut self, address: usize) -> Result<u8, CpuFault> {
+        let address = match self.address(address)? {
+            Some(address) => address,
+            None => return Ok(0),
+        };
+        let value = self.ram[address];
+        self.watchpoints
+            .check(Access::Read, address, value, self.instruction_pc);
+
+        Ok(value)
+    }
+
+    fn write(&mut self, address: usize, value: u8) -> Result<(), CpuFault> {
+        if let Some(address) = self.address(address)? {
+            self.ram[address] = value;
+            self.watchpoints
+                .check(Access::Write, address, value, self.instruction_pc);
+        }
+
+        Ok(())
+    }
+
+    /// Registers x to y inclusive, in descending order when x > y.
+    fn register_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+        if x <= y {
+            Box::new(x..=y)
+        } else {
+            Box::new((y..=x).rev())
         }
     }
 
-    fn fetch_instruction(&self) -> u16 {
-        ((self.ram[self.pc as usize] as u16) << 8) | (self.ram[self.pc as usize + 1]) as u16
+    /// Skips the next instruction, which is four bytes long for `F000 NNNN`.
+    fn skip(&mut self) {
+        self.pc = self.pc.wrapping_add(self.fetch_instruction().length());
+    }
+
+    fn shift_source(&self, x: usize, y: usize) -> u8 {
+        if self.quirks.shift_vy {
+            self.v[y]
+        } else {
+            self.v[x]
+        }
+    }
+
+    fn reset_flag(&mut self) {
+        if self.quirks.vf_reset {
+            self.v[0xf] = 0;
+        }
+    }
+
+    fn increment_index(&mut self, x: usize) {
+        match self.quirks.index_increment {
+            IndexIncrement::Unchanged => (),
+            IndexIncrement::ByX => self.i += x,
+            IndexIncrement::ByXPlusOne => self.i += x + 1,
+        }
+    }
+
+    fn fetch_instruction(&self) -> Instruction {
+        Instruction::fetch(self.ram(), self.pc as usize)
     }
 }
 
 #[cfg(test)]
 mod cpu_test {
     use super::*;
-    use crust::ROM_SIZE;
+    use crate::rom::RomBuffer;
+    use crate::{HIRES_HEIGHT, HIRES_WIDTH, ROM_SIZE, WIDTH};
 
     #[test]
     fn test_new_cpu() {
@@ -354,7 +915,7 @@ mod cpu_test {
 
     #[test]
     fn test_init_cpu() {
-        let mut rom: RomBuffer = [0; ROM_SIZE];
+        let mut rom: RomBuffer = vec![0; ROM_SIZE];
         rom[0] = 0x42;
         let cpu = CPU::init(&rom);
 
@@ -363,6 +924,21 @@ mod cpu_test {
         assert_eq!(cpu.ram[cpu.pc as usize], 0x42);
     }
 
+    #[test]
+    fn test_soft_reset() {
+        // LD V3, 0x42; CALL 0x200
+        let mut cpu = CPU::init(&[0x63, 0x42, 0x22, 0x00]);
+        cpu.run_frame(&State::new(), 2).unwrap();
+        cpu.poke(0x300, &[0xAB]);
+
+        cpu.soft_reset();
+
+        assert_eq!(cpu.pc, 0x200);
+        assert_eq!(cpu.sp, 0);
+        assert_eq!(cpu.v, [0; GENERAL_PURPOSE_REGISTERS]);
+        assert_eq!(cpu.ram[0x300], 0xAB);
+    }
+
     #[test]
     fn test_load_font() {
         let mut cpu = CPU::new();
@@ -373,7 +949,7 @@ mod cpu_test {
 
     #[test]
     fn test_load_rom() {
-        let mut rom: RomBuffer = [0; ROM_SIZE];
+        let mut rom: RomBuffer = vec![0; ROM_SIZE];
         let mut cpu = CPU::new();
 
         cpu.pc = 0x21;
@@ -419,36 +995,33 @@ mod cpu_test {
 
     #[test]
     fn test_fetch_instruction() {
-        let mut rom: RomBuffer = [0; ROM_SIZE];
+        let mut rom: RomBuffer = vec![0; ROM_SIZE];
 
         for (i, &elem) in [0x01, 0x02, 0x03, 0x04].iter().enumerate() {
-            rom[i as usize] = elem;
+            rom[i] = elem;
         }
 
         let mut cpu = CPU::init(&rom);
 
         let instruction = cpu.fetch_instruction();
-        assert_eq!(instruction, 0x0102);
+        assert_eq!(instruction.raw, 0x0102);
         cpu.pc += INSTRUCTION_LENGTH;
         let instruction = cpu.fetch_instruction();
-        assert_eq!(instruction, 0x0304);
+        assert_eq!(instruction.raw, 0x0304);
     }
 
     fn setup_instruction(instruction: u16) -> (CPU, State) {
-        let mut rom: RomBuffer = [0; ROM_SIZE];
         let state = State::new();
-        rom[0] = (instruction >> 8) as u8;
-        rom[1] = (instruction << 8 >> 8) as u8;
-        let cpu = CPU::init(&rom);
+        let cpu = CPU::init(&[(instruction >> 8) as u8, instruction as u8]);
 
-        return (cpu, state);
+        (cpu, state)
     }
 
     #[test]
     fn test_clear_screen() {
         let (mut cpu, state) = setup_instruction(0x00E0);
-        cpu.tick(&state);
-        assert_eq!(cpu.vram[0][0], false)
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.vram.get(0, 0), 0)
     }
 
     #[test]
@@ -456,7 +1029,7 @@ mod cpu_test {
         let (mut cpu, state) = setup_instruction(0x00EE);
         cpu.sp = 1;
         cpu.stack[0] = 0x42;
-        cpu.tick(&state);
+        cpu.tick(&state).unwrap();
         assert_eq!(cpu.sp, 0);
         assert_eq!(cpu.pc, 0x42);
     }
@@ -464,14 +1037,14 @@ mod cpu_test {
     #[test]
     fn test_jp_addr() {
         let (mut cpu, state) = setup_instruction(0x1234);
-        cpu.tick(&state);
+        cpu.tick(&state).unwrap();
         assert_eq!(cpu.pc, 0x234);
     }
 
     #[test]
     fn test_call_addr() {
         let (mut cpu, state) = setup_instruction(0x2234);
-        cpu.tick(&state);
+        cpu.tick(&state).unwrap();
         assert_eq!(cpu.stack[0], 0x202);
         assert_eq!(cpu.sp, 1);
         assert_eq!(cpu.pc, 0x234);
@@ -481,7 +1054,7 @@ mod cpu_test {
     fn test_se_reg_const() {
         let (mut cpu, state) = setup_instruction(0x3012);
         cpu.v[0] = 0x12;
-        cpu.tick(&state);
+        cpu.tick(&state).unwrap();
         assert_eq!(cpu.pc, 0x204)
     }
 
@@ -489,7 +1062,7 @@ mod cpu_test {
     fn test_se_reg_const_no_match() {
         let (mut cpu, state) = setup_instruction(0x3012);
         cpu.v[0] = 0x00;
-        cpu.tick(&state);
+        cpu.tick(&state).unwrap();
         assert_eq!(cpu.pc, 0x202)
     }
 
@@ -497,7 +1070,7 @@ mod cpu_test {
     fn test_sne_reg_const() {
         let (mut cpu, state) = setup_instruction(0x4012);
         cpu.v[0] = 0x00;
-        cpu.tick(&state);
+        cpu.tick(&state).unwrap();
         assert_eq!(cpu.pc, 0x204)
     }
 
@@ -505,7 +1078,590 @@ mod cpu_test {
     fn test_sne_reg_const_match() {
         let (mut cpu, state) = setup_instruction(0x4012);
         cpu.v[0] = 0x12;
-        cpu.tick(&state);
+        cpu.tick(&state).unwrap();
         assert_eq!(cpu.pc, 0x202)
     }
+
+    fn setup_quirks(instruction: u16, quirks: Quirks) -> (CPU, State) {
+        let (mut cpu, state) = setup_instruction(instruction);
+        cpu.set_quirks(quirks);
+
+        (cpu, state)
+    }
+
+    #[test]
+    fn test_shr_in_place() {
+        let (mut cpu, state) = setup_quirks(0x8126, Quirks::chip48());
+        cpu.v[1] = 0x05;
+        cpu.v[2] = 0x80;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0x02);
+        assert_eq!(cpu.v[0xf], 1);
+    }
+
+    #[test]
+    fn test_shr_vy() {
+        let (mut cpu, state) = setup_quirks(0x8126, Quirks::cosmac_vip());
+        cpu.v[1] = 0x05;
+        cpu.v[2] = 0x80;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0x40);
+        assert_eq!(cpu.v[0xf], 0);
+    }
+
+    #[test]
+    fn test_add_reg_reg() {
+        let (mut cpu, state) = setup_instruction(0x8124);
+        cpu.v[1] = 0xFF;
+        cpu.v[2] = 0x02;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0x01);
+        assert_eq!(cpu.v[0xf], 1);
+    }
+
+    #[test]
+    fn test_sub_reg_reg() {
+        let (mut cpu, state) = setup_instruction(0x8125);
+        cpu.v[1] = 0x05;
+        cpu.v[2] = 0x03;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0x02);
+        assert_eq!(cpu.v[0xf], 1);
+
+        let (mut cpu, state) = setup_instruction(0x8125);
+        cpu.v[1] = 0x03;
+        cpu.v[2] = 0x05;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0xFE);
+        assert_eq!(cpu.v[0xf], 0);
+    }
+
+    #[test]
+    fn test_subn_reg_reg() {
+        let (mut cpu, state) = setup_instruction(0x8127);
+        cpu.v[1] = 0x03;
+        cpu.v[2] = 0x05;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0x02);
+        assert_eq!(cpu.v[0xf], 1);
+
+        let (mut cpu, state) = setup_instruction(0x8127);
+        cpu.v[1] = 0x05;
+        cpu.v[2] = 0x03;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0xFE);
+        assert_eq!(cpu.v[0xf], 0);
+    }
+
+    #[test]
+    fn test_shl() {
+        let (mut cpu, state) = setup_instruction(0x812E);
+        cpu.v[1] = 0x81;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0x02);
+        assert_eq!(cpu.v[0xf], 1);
+
+        let (mut cpu, state) = setup_instruction(0x812E);
+        cpu.v[1] = 0x41;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[1], 0x82);
+        assert_eq!(cpu.v[0xf], 0);
+    }
+
+    #[test]
+    fn test_flag_overrides_vf_operand() {
+        let (mut cpu, state) = setup_instruction(0x8F15);
+        cpu.v[0xf] = 0x01;
+        cpu.v[1] = 0x05;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[0xf], 0);
+    }
+
+    #[test]
+    fn test_ld_reg_key() {
+        let (mut cpu, state) = setup_instruction(0xF30A);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x200);
+
+        cpu.tick(&State::from_raw(1 << 0xB)).unwrap();
+        assert_eq!(cpu.v[3], 0xB);
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    fn setup_last_word(instruction: [u8; 2]) -> (CPU, State) {
+        let (mut cpu, state) = setup_instruction(0x0000);
+        cpu.set_memory_size(MEMORY_SIZE).unwrap();
+        cpu.poke(MEMORY_SIZE - 2, &instruction);
+        cpu.set_pc((MEMORY_SIZE - 2) as u16);
+
+        (cpu, state)
+    }
+
+    #[test]
+    fn test_ld_reg_key_last_word() {
+        let (mut cpu, state) = setup_last_word([0xF3, 0x0A]);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0xFFFE);
+
+        cpu.tick(&State::from_raw(1 << 0xB)).unwrap();
+        assert_eq!(cpu.v[3], 0xB);
+        assert_eq!(cpu.pc, 0x0000);
+    }
+
+    #[test]
+    fn test_skp_high_register() {
+        let (mut cpu, state) = setup_instruction(0xE09E);
+        cpu.v[0] = 0x20;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+
+        let (mut cpu, _) = setup_instruction(0xE0A1);
+        cpu.v[0] = 0x1B;
+        cpu.tick(&State::from_raw(1 << 0xB)).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn test_ld_ind_reg_increment() {
+        let (mut cpu, state) = setup_quirks(0xF255, Quirks::cosmac_vip());
+        cpu.i = 0x300;
+        cpu.v[0..3].copy_from_slice(&[1, 2, 3]);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.ram[0x300..0x303], [1, 2, 3]);
+        assert_eq!(cpu.i, 0x303);
+
+        let (mut cpu, state) = setup_quirks(0xF265, Quirks::chip48());
+        cpu.i = 0x300;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.i, 0x302);
+
+        let (mut cpu, state) = setup_quirks(0xF265, Quirks::super_chip());
+        cpu.i = 0x300;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.i, 0x300);
+    }
+
+    #[test]
+    fn test_jp_vx_addr() {
+        let (mut cpu, state) = setup_quirks(0xB220, Quirks::super_chip());
+        cpu.v[0] = 0x01;
+        cpu.v[2] = 0x02;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x222);
+
+        let (mut cpu, state) = setup_quirks(0xB220, Quirks::cosmac_vip());
+        cpu.v[0] = 0x01;
+        cpu.v[2] = 0x02;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x221);
+    }
+
+    #[test]
+    fn test_vf_reset() {
+        let (mut cpu, state) = setup_quirks(0x8121, Quirks::cosmac_vip());
+        cpu.v[0xf] = 1;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[0xf], 0);
+
+        let (mut cpu, state) = setup_quirks(0x8121, Quirks::super_chip());
+        cpu.v[0xf] = 1;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[0xf], 1);
+    }
+
+    #[test]
+    fn test_drw_clip_and_wrap() {
+        for &(quirks, wrapped) in [(Quirks::crust(), 1), (Quirks::super_chip(), 0)].iter() {
+            let (mut cpu, state) = setup_quirks(0xD011, quirks);
+            cpu.i = 0x300;
+            cpu.ram[0x300] = 0xFF;
+            cpu.v[0] = (WIDTH - 4) as u8;
+            cpu.v[1] = 0;
+            cpu.tick(&state).unwrap();
+            assert_eq!(cpu.vram.get(WIDTH - 1, 0), 1);
+            assert_eq!(cpu.vram.get(0, 0), wrapped);
+        }
+    }
+
+    #[test]
+    fn test_drw_display_wait() {
+        let mut cpu = CPU::init(&[0xD0, 0x01, 0xD0, 0x01]);
+        let state = State::new();
+        cpu.set_quirks(Quirks::cosmac_vip());
+
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        cpu.vblank();
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x204);
+    }
+
+    #[test]
+    fn test_drw_display_wait_last_word() {
+        let (mut cpu, state) = setup_last_word([0xD0, 0x01]);
+        cpu.set_quirks(Quirks::cosmac_vip());
+        cpu.vblank = false;
+
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0xFFFE);
+        cpu.vblank();
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x0000);
+    }
+
+    #[test]
+    fn test_high_low() {
+        let mut cpu = CPU::init(&[0x00, 0xFF, 0x00, 0xFE]);
+        let state = State::new();
+
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.vram.width(), HIRES_WIDTH);
+        assert_eq!(cpu.vram.height(), HIRES_HEIGHT);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.vram.width(), WIDTH);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let (mut cpu, state) = setup_instruction(0x00C2);
+        cpu.vram.set(0, 0, 1);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.vram.get(0, 2), 1);
+
+        let (mut cpu, state) = setup_instruction(0x00FB);
+        cpu.vram.set(0, 0, 1);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.vram.get(4, 0), 1);
+
+        let (mut cpu, state) = setup_instruction(0x00FC);
+        cpu.vram.set(4, 0, 1);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.vram.get(0, 0), 1);
+    }
+
+    #[test]
+    fn test_exit() {
+        let (mut cpu, state) = setup_instruction(0x00FD);
+        cpu.tick(&state).unwrap();
+        assert!(cpu.halted());
+        assert_eq!(cpu.pc, 0x200);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x200);
+    }
+
+    #[test]
+    fn test_exit_last_word() {
+        let (mut cpu, state) = setup_last_word([0x00, 0xFD]);
+        cpu.tick(&state).unwrap();
+        assert!(cpu.halted());
+        assert_eq!(cpu.pc, 0xFFFE);
+    }
+
+    #[test]
+    fn test_drw_big_sprite() {
+        let (mut cpu, state) = setup_instruction(0xD010);
+        cpu.vram.set_resolution(Resolution::High);
+        cpu.i = 0x300;
+        for idx in 0..32 {
+            cpu.ram[0x300 + idx] = 0xFF;
+        }
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.vram.get(15, 15), 1);
+        assert_eq!(cpu.vram.get(16, 15), 0);
+        assert_eq!(cpu.vram.get(15, 16), 0);
+        assert_eq!(cpu.v[0xf], 0);
+    }
+
+    #[test]
+    fn test_ld_big_font_char() {
+        let (mut cpu, state) = setup_instruction(0xF130);
+        cpu.v[1] = 0x2;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.i, BIG_FONT_START + 20);
+        assert_eq!(cpu.ram[cpu.i..cpu.i + 10], BIG_FONT[20..30]);
+    }
+
+    #[test]
+    fn test_ld_flags() {
+        let (mut cpu, state) = setup_instruction(0xF275);
+        cpu.v[0..4].copy_from_slice(&[1, 2, 3, 4]);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.flags[0..4], [1, 2, 3, 0]);
+
+        let (mut cpu, state) = setup_instruction(0xF185);
+        cpu.flags[0..2].copy_from_slice(&[5, 6]);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[0..2], [5, 6]);
+    }
+
+    #[test]
+    fn test_ld_i_long() {
+        let mut cpu = CPU::init(&[0xF0, 0x00, 0xAB, 0xCD]);
+        cpu.tick(&State::new()).unwrap();
+
+        assert_eq!(cpu.i, 0xABCD);
+        assert_eq!(cpu.pc, 0x204);
+    }
+
+    #[test]
+    fn test_skip_long_instruction() {
+        let mut cpu = CPU::init(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34]);
+        cpu.tick(&State::new()).unwrap();
+
+        assert_eq!(cpu.pc, 0x206);
+    }
+
+    #[test]
+    fn test_save_load_range() {
+        let (mut cpu, state) = setup_instruction(0x5242);
+        cpu.i = 0x300;
+        cpu.v[2..5].copy_from_slice(&[1, 2, 3]);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.ram[0x300..0x303], [1, 2, 3]);
+        assert_eq!(cpu.i, 0x300);
+
+        let (mut cpu, state) = setup_instruction(0x5423);
+        cpu.i = 0x300;
+        cpu.ram[0x300..0x303].copy_from_slice(&[1, 2, 3]);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[2..5], [3, 2, 1]);
+    }
+
+    #[test]
+    fn test_plane_drawing() {
+        let (mut cpu, state) = setup_instruction(0xD011);
+        cpu.plane = 0x3;
+        cpu.i = 0x300;
+        cpu.ram[0x300..0x302].copy_from_slice(&[0x80, 0x40]);
+        cpu.tick(&state).unwrap();
+
+        assert_eq!(cpu.vram.get(0, 0), 1);
+        assert_eq!(cpu.vram.get(1, 0), 2);
+    }
+
+    #[test]
+    fn test_plane_clear() {
+        let (mut cpu, state) = setup_instruction(0x00E0);
+        cpu.plane = 0x2;
+        cpu.vram.set(0, 0, 3);
+        cpu.tick(&state).unwrap();
+
+        assert_eq!(cpu.vram.get(0, 0), 1);
+    }
+
+    #[test]
+    fn test_plane_const() {
+        let (mut cpu, state) = setup_instruction(0xF201);
+        cpu.tick(&state).unwrap();
+
+        assert_eq!(cpu.plane(), 0x2);
+    }
+
+    #[test]
+    fn test_audio() {
+        let (mut cpu, state) = setup_instruction(0xF002);
+        cpu.i = 0x300;
+        for idx in 0..PATTERN_SIZE {
+            cpu.ram[0x300 + idx] = idx as u8;
+        }
+        cpu.tick(&state).unwrap();
+
+        assert_eq!(
+            cpu.audio_pattern()[..],
+            cpu.ram[0x300..0x300 + PATTERN_SIZE]
+        );
+    }
+
+    #[test]
+    fn test_pitch_reg() {
+        let (mut cpu, state) = setup_instruction(0xF13A);
+        cpu.v[1] = 0x80;
+        cpu.tick(&state).unwrap();
+
+        assert_eq!(cpu.pitch(), 0x80);
+    }
+
+    #[test]
+    fn test_ret_stack_underflow() {
+        let (mut cpu, state) = setup_instruction(0x00EE);
+
+        assert_eq!(
+            cpu.tick(&state),
+            Err(CpuFault::StackUnderflow { pc: 0x200 })
+        );
+        assert_eq!(cpu.pc, 0x200);
+        assert_eq!(cpu.fault(), Some(CpuFault::StackUnderflow { pc: 0x200 }));
+        assert!(cpu.tick(&state).is_err());
+    }
+
+    #[test]
+    fn test_ret_stack_underflow_wrap() {
+        let (mut cpu, state) = setup_instruction(0x00EE);
+        cpu.set_fault_policy(FaultPolicy::uniform(FaultAction::Wrap));
+        cpu.stack[STACK_SIZE - 1] = 0x42;
+
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.sp, STACK_SIZE - 1);
+        assert_eq!(cpu.pc, 0x42);
+    }
+
+    #[test]
+    fn test_call_stack_overflow() {
+        let (mut cpu, state) = setup_instruction(0x2234);
+        cpu.sp = STACK_SIZE;
+
+        assert_eq!(cpu.tick(&state), Err(CpuFault::StackOverflow { pc: 0x200 }));
+
+        let (mut cpu, state) = setup_instruction(0x2234);
+        cpu.set_fault_policy(FaultPolicy::uniform(FaultAction::Ignore));
+        cpu.sp = STACK_SIZE;
+
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.sp, STACK_SIZE);
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let (mut cpu, state) = setup_instruction(0xF033);
+        cpu.i = CHIP8_MEMORY_SIZE - 1;
+
+        assert_eq!(
+            cpu.tick(&state),
+            Err(CpuFault::OutOfBounds {
+                pc: 0x200,
+                address: CHIP8_MEMORY_SIZE
+            })
+        );
+
+        let mut cpu = CPU::init(&[0xF0, 0x33]);
+        cpu.set_memory_size(MEMORY_SIZE).unwrap();
+        cpu.i = CHIP8_MEMORY_SIZE - 1;
+
+        cpu.tick(&State::new()).unwrap();
+        assert_eq!(cpu.ram()[CHIP8_MEMORY_SIZE], 0);
+    }
+
+    #[test]
+    fn test_set_profile() {
+        let mut cpu = CPU::init(&[0; CHIP8_MEMORY_SIZE - 0x200]);
+        cpu.set_profile(&"schip".parse().unwrap()).unwrap();
+        assert_eq!(cpu.quirks(), &Quirks::super_chip());
+        assert_eq!(cpu.ram().len(), CHIP8_MEMORY_SIZE);
+
+        let mut cpu = CPU::init(&[0; CHIP8_MEMORY_SIZE - 0x1FF]);
+        assert!(cpu.set_profile(&"schip".parse().unwrap()).is_err());
+        cpu.set_profile(&"xochip".parse().unwrap()).unwrap();
+        assert_eq!(cpu.ram().len(), MEMORY_SIZE);
+    }
+
+    #[test]
+    fn test_out_of_bounds_wrap() {
+        let (mut cpu, state) = setup_instruction(0xF233);
+        cpu.set_fault_policy(FaultPolicy::uniform(FaultAction::Wrap));
+        cpu.i = CHIP8_MEMORY_SIZE - 1;
+        cpu.v[2] = 123;
+
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.ram[CHIP8_MEMORY_SIZE - 1], 1);
+        assert_eq!(cpu.ram[0..2], [2, 3]);
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        let (mut cpu, state) = setup_instruction(0x5121);
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(
+            cpu.take_ignored_fault(),
+            Some(CpuFault::UnknownOpcode {
+                pc: 0x200,
+                opcode: 0x5121
+            })
+        );
+
+        // Only the first ignored fault is kept.
+        cpu.pc = 0x200;
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.take_ignored_fault(), None);
+        assert_eq!(cpu.ignored_faults(), 2);
+
+        let (mut cpu, state) = setup_instruction(0x5121);
+        cpu.set_fault_policy(FaultPolicy::uniform(FaultAction::Halt));
+        assert_eq!(
+            cpu.tick(&state),
+            Err(CpuFault::UnknownOpcode {
+                pc: 0x200,
+                opcode: 0x5121
+            })
+        );
+    }
+
+    #[test]
+    fn test_rnd_seeded() {
+        let mut rom = Vec::new();
+        for idx in 0..8 {
+            rom.extend_from_slice(&[0xC0 | idx as u8, 0xFF]);
+        }
+
+        let run = |seed| {
+            let mut cpu = CPU::init(&rom);
+            cpu.set_seed(seed);
+            for _ in 0..8 {
+                cpu.tick(&State::new()).unwrap();
+            }
+            cpu.v
+        };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let (mut cpu, state) = setup_instruction(0xC0FF);
+        cpu.set_seed(7);
+        let snapshot = cpu.snapshot();
+
+        cpu.tick(&state).unwrap();
+        let first = cpu.v[0];
+        cpu.restore(&snapshot);
+        assert_eq!(cpu.pc, 0x200);
+
+        cpu.tick(&state).unwrap();
+        assert_eq!(cpu.v[0], first);
+        assert_eq!(cpu.snapshot().pc, 0x202);
+    }
+
+    #[test]
+    fn test_restore_clears_fault() {
+        let (mut cpu, state) = setup_instruction(0x00EE);
+        let snapshot = cpu.snapshot();
+
+        assert!(cpu.tick(&state).is_err());
+        cpu.restore(&snapshot);
+        assert_eq!(cpu.fault(), None);
+    }
+
+    #[test]
+    fn test_run_frame() {
+        // ADD V0, 1; JP 0x200
+        let mut cpu = CPU::init(&[0x70, 0x01, 0x12, 0x00]);
+        cpu.dt = 2;
+
+        cpu.run_frame(&State::new(), 10).unwrap();
+        assert_eq!(cpu.v[0], 5);
+        assert_eq!(cpu.dt, 1);
+    }
+
+    #[test]
+    fn test_run_frame_fault() {
+        let (mut cpu, state) = setup_instruction(0x00EE);
+        cpu.dt = 2;
+
+        assert!(cpu.run_frame(&state, 10).is_err());
+        assert_eq!(cpu.dt, 2);
+    }
 }
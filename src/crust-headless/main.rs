@@ -0,0 +1,242 @@
+extern crate clap;
+
+use std::fs::{self, File};
+use std::io;
+use std::process;
+
+use clap::{App, Arg, ArgMatches};
+
+use crust::cpu::CPU;
+use crust::decoder::CLASSES;
+use crust::fault::{FaultPolicy, ACTIONS};
+use crust::headless::{self, Limit};
+use crust::quirks::{Profile, PROFILES};
+use crust::rom::load_rom;
+use crust::screenshot::{write_vram, ImageFormat, FORMATS};
+use crust::script::InputScript;
+use crust::trace::{Trace, TraceOptions};
+
+const FRAMES: &str = "60";
+const INSTRUCTIONS_PER_FRAME: &str = "8";
+const QUIRKS: &str = "crust";
+const FORMAT: &str = "ascii";
+
+const EXIT_ERROR: i32 = 1;
+const EXIT_FAULT: i32 = 2;
+
+/// Why a run failed, selecting the exit code.
+enum Failure {
+    Error(String),
+    Fault(String),
+}
+
+impl From<String> for Failure {
+    fn from(error: String) -> Self {
+        Failure::Error(error)
+    }
+}
+
+fn parse_number(value: &str, name: &str) -> Result<u64, String> {
+    value
+        .parse::<u64>()
+        .map_err(|_| format!("Couldn't parse value '{}' as a {}.", value, name))
+}
+
+fn parse_limit(matches: &ArgMatches) -> Result<Limit, String> {
+    if let Some(cycles) = matches.value_of("cycles") {
+        return Ok(Limit::Cycles(parse_number(cycles, "number of cycles")?));
+    }
+
+    let frames = matches.value_of("frames").unwrap_or(FRAMES);
+    Ok(Limit::Frames(parse_number(frames, "number of frames")?))
+}
+
+fn parse_trace(matches: &ArgMatches) -> Result<Option<Trace>, String> {
+    TraceOptions {
+        path: matches.value_of("trace"),
+        pc_range: matches.value_of("trace_pc"),
+        classes: matches
+            .values_of("trace_class")
+            .map_or_else(Vec::new, Iterator::collect),
+        memory_hash: matches.is_present("trace_memory"),
+    }
+    .create()
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Failure> {
+    let limit = parse_limit(matches)?;
+    let instructions = matches.value_of("ipf").unwrap_or(INSTRUCTIONS_PER_FRAME);
+    let instructions_per_frame = match parse_number(instructions, "number of instructions")? {
+        0 => {
+            return Err(Failure::Error(format!(
+                "Couldn't parse value '{}' as a number of instructions.",
+                instructions
+            )))
+        }
+        value => value as usize,
+    };
+    let profile: Profile = matches.value_of("quirks").unwrap_or(QUIRKS).parse()?;
+    let format: ImageFormat = matches.value_of("format").unwrap_or(FORMAT).parse()?;
+    let fault_policy = match matches.value_of("on_fault") {
+        Some(action) => FaultPolicy::uniform(action.parse()?),
+        None => FaultPolicy::default(),
+    };
+    let script = match matches.value_of("input") {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|error| format!("{}: {}", path, error))?
+            .parse::<InputScript>()
+            .map_err(|error| format!("{}: {}", path, error))?,
+        None => InputScript::new(),
+    };
+
+    let rom_path = matches.value_of("ROM").unwrap();
+    let rom = load_rom(rom_path).map_err(|error| error.to_string())?;
+    let mut cpu = CPU::init(&rom);
+    cpu.set_profile(&profile)?;
+    cpu.set_fault_policy(fault_policy);
+    if let Some(seed) = matches.value_of("seed") {
+        cpu.set_seed(parse_number(seed, "seed")?);
+    }
+    cpu.set_trace(parse_trace(matches)?);
+
+    let result = headless::run(&mut cpu, instructions_per_frame, limit, &script);
+    if let Some(fault) = cpu.take_ignored_fault() {
+        eprintln!(
+            "Ignored {} CPU faults, the first one: {}",
+            cpu.ignored_faults(),
+            fault
+        );
+    }
+    if let Some(trace) = cpu.take_trace() {
+        trace.finish().map_err(|error| error.to_string())?;
+    }
+
+    // The screen is dumped even after a fault, to help diagnosing it.
+    let written = match matches.value_of("output") {
+        Some(path) => File::create(path).and_then(|file| write_vram(cpu.get_vram(), format, file)),
+        None => write_vram(cpu.get_vram(), format, io::stdout()),
+    };
+    written.map_err(|error| error.to_string())?;
+
+    result
+        .map(|_| ())
+        .map_err(|fault| Failure::Fault(fault.to_string()))
+}
+
+fn main() {
+    let matches = App::new("crust-headless")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Runs a Chip-8 rom without a display and dumps its screen")
+        .arg(
+            Arg::with_name("ROM")
+                .help("path to the rom file")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("frames")
+                .short("f")
+                .long("frames")
+                .value_name("frames")
+                .help("Stop after this many 60 Hz frames (60 by default)"),
+        )
+        .arg(
+            Arg::with_name("cycles")
+                .long("cycles")
+                .value_name("cycles")
+                .conflicts_with("frames")
+                .help("Stop after this many instructions"),
+        )
+        .arg(
+            Arg::with_name("ipf")
+                .short("p")
+                .long("ipf")
+                .value_name("ipf")
+                .help("Set the number of instructions executed per 60 Hz frame"),
+        )
+        .arg(
+            Arg::with_name("quirks")
+                .short("q")
+                .long("quirks")
+                .value_name("quirks")
+                .possible_values(&PROFILES)
+                .help("Select the behavior of ambiguous instructions"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .short("s")
+                .long("seed")
+                .value_name("seed")
+                .help("Seed the random number generator for reproducible runs"),
+        )
+        .arg(
+            Arg::with_name("on_fault")
+                .long("on-fault")
+                .value_name("on_fault")
+                .possible_values(&ACTIONS)
+                .help("Halt, ignore or wrap around on CPU faults"),
+        )
+        .arg(
+            Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .value_name("input")
+                .help("Press keys from a script of '<frame> <keys>' lines"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("format")
+                .possible_values(&FORMATS)
+                .help("Format of the screen dump (ascii by default)"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("output")
+                .help("Write the screen dump to a file instead of stdout"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .value_name("file")
+                .help("Write a line per executed instruction to a file"),
+        )
+        .arg(
+            Arg::with_name("trace_pc")
+                .long("trace-pc")
+                .value_name("range")
+                .requires("trace")
+                .help("Only trace instructions between two hex addresses, as 200-2ff"),
+        )
+        .arg(
+            Arg::with_name("trace_class")
+                .long("trace-class")
+                .value_name("class")
+                .requires("trace")
+                .multiple(true)
+                .use_delimiter(true)
+                .possible_values(&CLASSES)
+                .help("Only trace instructions of these opcode classes"),
+        )
+        .arg(
+            Arg::with_name("trace_memory")
+                .long("trace-memory")
+                .requires("trace")
+                .help("Add a hash of the memory to every trace line"),
+        )
+        .get_matches();
+
+    match run(&matches) {
+        Ok(()) => {}
+        Err(Failure::Error(error)) => {
+            eprintln!("Error: {}", error);
+            process::exit(EXIT_ERROR)
+        }
+        Err(Failure::Fault(fault)) => {
+            eprintln!("CPU fault: {}", fault);
+            process::exit(EXIT_FAULT)
+        }
+    }
+}
@@ -0,0 +1,131 @@
+extern crate clap;
+
+use std::fs;
+use std::process;
+
+use clap::{App, Arg, ArgMatches};
+
+use crust::tracediff::{first_divergence, Divergence, TraceLine};
+
+const CONTEXT: &str = "5";
+
+const EXIT_SAME: i32 = 0;
+const EXIT_DIFFERENT: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
+/// Raw and parsed lines of a trace file.
+struct TraceFile {
+    path: String,
+    text: Vec<String>,
+    lines: Vec<TraceLine>,
+}
+
+fn read_trace(path: &str) -> Result<TraceFile, String> {
+    let content = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let text: Vec<String> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect();
+    let lines = text
+        .iter()
+        .enumerate()
+        .map(|(number, line)| {
+            line.parse()
+                .map_err(|error| format!("{}:{}: {}", path, number + 1, error))
+        })
+        .collect::<Result<Vec<TraceLine>, String>>()?;
+
+    Ok(TraceFile {
+        path: path.to_string(),
+        text,
+        lines,
+    })
+}
+
+fn print_divergence(a: &TraceFile, b: &TraceFile, divergence: &Divergence, context: usize) {
+    let location = |trace: &TraceFile, index: Option<usize>| match index {
+        Some(index) => format!("line {} of {}", index + 1, trace.path),
+        None => format!("end of {}", trace.path),
+    };
+    let cycle = divergence
+        .a
+        .map(|index| a.lines[index].cycle)
+        .or_else(|| divergence.b.map(|index| b.lines[index].cycle))
+        .unwrap_or_default();
+
+    println!(
+        "First divergence at cycle {} ({}, {}): {}",
+        cycle,
+        location(a, divergence.a),
+        location(b, divergence.b),
+        divergence.differences.join(", ")
+    );
+    println!("--- {}\n+++ {}", a.path, b.path);
+
+    let end = divergence.a.unwrap_or(a.text.len());
+    for line in a.text[end.saturating_sub(context)..end].iter() {
+        println!("  {}", line);
+    }
+    if let Some(index) = divergence.a {
+        for line in a.text[index..(index + context + 1).min(a.text.len())].iter() {
+            println!("- {}", line);
+        }
+    }
+    if let Some(index) = divergence.b {
+        for line in b.text[index..(index + context + 1).min(b.text.len())].iter() {
+            println!("+ {}", line);
+        }
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<bool, String> {
+    let context = matches.value_of("context").unwrap_or(CONTEXT);
+    let context = context
+        .parse::<usize>()
+        .map_err(|_| format!("Couldn't parse value '{}' as a number of lines.", context))?;
+    let a = read_trace(matches.value_of("A").unwrap())?;
+    let b = read_trace(matches.value_of("B").unwrap())?;
+
+    match first_divergence(&a.lines, &b.lines) {
+        Some(divergence) => {
+            print_divergence(&a, &b, &divergence, context);
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
+fn main() {
+    let matches = App::new("crust-tracediff")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Finds the first divergence between two Chip-8 execution traces")
+        .arg(
+            Arg::with_name("A")
+                .help("path to the first trace")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("B")
+                .help("path to the second trace")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("context")
+                .short("C")
+                .long("context")
+                .value_name("lines")
+                .help("Show this many lines around the divergence (5 by default)"),
+        )
+        .get_matches();
+
+    match run(&matches) {
+        Ok(true) => process::exit(EXIT_SAME),
+        Ok(false) => process::exit(EXIT_DIFFERENT),
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(EXIT_ERROR)
+        }
+    }
+}
@@ -0,0 +1,192 @@
+extern crate sdl2;
+
+use sdl2::pixels::Color;
+
+use crate::context::Context;
+use crate::keyboard::Scroll;
+use crate::text::{Page, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crust::cpu::CPU;
+use crust::inspector::{self, MemoryActivity};
+
+const SCALE: usize = 2;
+const CELL_WIDTH: usize = (GLYPH_WIDTH + 1) * SCALE;
+const CELL_HEIGHT: usize = (GLYPH_HEIGHT + 2) * SCALE;
+
+const COLUMNS: usize = 80;
+const ROWS: usize = 33;
+// Columns of the panels, whose titles are on the first row.
+const REGISTERS_COLUMN: usize = 1;
+const DISASSEMBLY_COLUMN: usize = 16;
+const MEMORY_COLUMN: usize = 46;
+const STACK_ROW: usize = 15;
+
+const MEMORY_ROWS: usize = ROWS - 2;
+const BYTES_PER_ROW: usize = 8;
+/// Updates during which a changed byte stays highlighted.
+const RECENT: u64 = 60;
+
+/// Colors of the text, indexing the rectangles of a page.
+#[derive(Clone, Copy)]
+enum Ink {
+    Text,
+    Dim,
+    Current,
+    Changed,
+}
+
+const INKS: [Color; 4] = [
+    Color {
+        r: 220,
+        g: 220,
+        b: 220,
+        a: 255,
+    },
+    Color {
+        r: 110,
+        g: 110,
+        b: 110,
+        a: 255,
+    },
+    Color {
+        r: 255,
+        g: 210,
+        b: 60,
+        a: 255,
+    },
+    Color {
+        r: 255,
+        g: 80,
+        b: 80,
+        a: 255,
+    },
+];
+
+/// Window showing the registers, the stack, the disassembly around PC and
+/// a scrolling view of the memory, changed bytes being highlighted.
+pub struct DebugWindow {
+    canvas: sdl2::render::WindowCanvas,
+    activity: MemoryActivity,
+    // First address of the memory view, which follows I when unset.
+    memory_start: Option<usize>,
+}
+
+impl DebugWindow {
+    /// Opens the window at `position`.
+    pub fn new(ctx: &Context, position: (i32, i32), cpu: &CPU) -> Self {
+        let window = ctx
+            .as_raw()
+            .video()
+            .unwrap()
+            .window(
+                "Crust debugger",
+                (COLUMNS * CELL_WIDTH) as u32,
+                (ROWS * CELL_HEIGHT) as u32,
+            )
+            .position(position.0, position.1)
+            .build()
+            .unwrap();
+
+        Self {
+            canvas: window.into_canvas().build().unwrap(),
+            activity: MemoryActivity::new(cpu.ram()),
+            memory_start: None,
+        }
+    }
+
+    pub fn scroll(&mut self, scroll: Scroll, cpu: &CPU) {
+        self.memory_start = match scroll {
+            Scroll::Rows(rows) => {
+                let start = self.memory_start(cpu) as i64 + rows as i64 * BYTES_PER_ROW as i64;
+                let last = (cpu.memory_size() - MEMORY_ROWS * BYTES_PER_ROW) as i64;
+                Some(start.max(0).min(last) as usize)
+            }
+            Scroll::Index => None,
+        }
+    }
+
+    fn memory_start(&self, cpu: &CPU) -> usize {
+        self.memory_start.unwrap_or_else(|| {
+            let row = (cpu.i() / BYTES_PER_ROW).saturating_sub(MEMORY_ROWS / 4);
+            (row * BYTES_PER_ROW).min(cpu.memory_size() - MEMORY_ROWS * BYTES_PER_ROW)
+        })
+    }
+
+    /// Takes note of the bytes changed since the last update, and redraws.
+    pub fn update(&mut self, cpu: &CPU) {
+        self.activity.update(cpu.ram());
+
+        let mut page = Page::new(SCALE, INKS.len());
+        self.draw_registers(&mut page, cpu);
+        self.draw_disassembly(&mut page, cpu);
+        self.draw_memory(&mut page, cpu);
+
+        self.canvas.set_draw_color(Color::RGB(20, 20, 30));
+        self.canvas.clear();
+        for (ink, &color) in INKS.iter().enumerate() {
+            self.canvas.set_draw_color(color);
+            self.canvas
+                .fill_rects(page.rects(ink))
+                .expect("Could not draw the debugger");
+        }
+        self.canvas.present()
+    }
+
+    fn draw_registers(&self, page: &mut Page, cpu: &CPU) {
+        page.text(REGISTERS_COLUMN, 0, "REGISTERS", Ink::Dim as usize);
+        for (row, line) in inspector::registers(cpu).iter().enumerate() {
+            page.text(REGISTERS_COLUMN, row + 1, line, Ink::Text as usize);
+        }
+
+        page.text(REGISTERS_COLUMN, STACK_ROW, "STACK", Ink::Dim as usize);
+        for (row, line) in inspector::stack(cpu).iter().enumerate() {
+            let ink = if row < cpu.sp() { Ink::Text } else { Ink::Dim };
+            page.text(REGISTERS_COLUMN, STACK_ROW + 1 + row, line, ink as usize);
+        }
+    }
+
+    fn draw_disassembly(&self, page: &mut Page, cpu: &CPU) {
+        page.text(DISASSEMBLY_COLUMN, 0, "DISASSEMBLY", Ink::Dim as usize);
+
+        let context = (ROWS - 2) / 2;
+        let instructions = inspector::disassemble(cpu.ram(), cpu.pc() as usize, context);
+        for (row, (address, instruction)) in instructions.iter().enumerate() {
+            let ink = if *address == cpu.pc() as usize {
+                Ink::Current
+            } else {
+                Ink::Text
+            };
+            let line = format!("{:04x} {:04x} {}", address, instruction.raw, instruction);
+            page.text(DISASSEMBLY_COLUMN, row + 1, &line, ink as usize);
+        }
+    }
+
+    fn draw_memory(&self, page: &mut Page, cpu: &CPU) {
+        page.text(MEMORY_COLUMN, 0, "MEMORY", Ink::Dim as usize);
+
+        let start = self.memory_start(cpu);
+        for row in 0..MEMORY_ROWS {
+            let address = start + row * BYTES_PER_ROW;
+            page.text(
+                MEMORY_COLUMN,
+                row + 1,
+                &format!("{:04x}", address),
+                Ink::Dim as usize,
+            );
+
+            for offset in 0..BYTES_PER_ROW {
+                let byte = address + offset;
+                let ink = match self.activity.age(byte) {
+                    Some(age) if age < RECENT => Ink::Changed,
+                    _ if byte == cpu.i() => Ink::Current,
+                    _ => Ink::Text,
+                };
+                page.text(
+                    MEMORY_COLUMN + 5 + offset * 3,
+                    row + 1,
+                    &format!("{:02x}", cpu.ram()[byte]),
+                    ink as usize,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,992 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::cpu::CPU;
+use crate::decoder::{Instruction, Opcode};
+use crate::expression::Expression;
+use crate::fault::CpuFault;
+use crate::history::History;
+use crate::inspector;
+use crate::keypad::State;
+use crate::trace::format_line;
+use crate::watch::{Access, WatchHit, Watchpoint};
+use crate::FRAME_RATE;
+
+/// Instructions `next` runs at most while waiting for a call to return.
+const NEXT_LIMIT: usize = 1_000_000;
+/// Instructions shown before and after PC by `disassemble`.
+const DISASSEMBLY_CONTEXT: usize = 5;
+const DUMP_LENGTH: usize = 0x40;
+/// Seconds of execution between two checkpoints of the history.
+const CHECKPOINT_INTERVAL: usize = 1;
+/// Checkpoints kept, so how many seconds can be run backwards.
+const CHECKPOINTS: usize = 60;
+
+pub const HELP: &str = "\
+step [n]          s    execute n instructions (1 by default)
+next              n    execute one instruction, running calls to completion
+continue          c    resume until a breakpoint
+reverse-step [n]  rs   go back n instructions (1 by default)
+reverse-continue  rc   go back to the previous breakpoint or watchpoint hit
+break <addr> [if <expr>]
+                  b    set a breakpoint, paused on when the condition holds
+tracepoint <addr> [if <expr>]
+                  tp   set a breakpoint which only logs the CPU state
+condition <addr> [expr]
+                  cond set or remove the condition of a breakpoint
+ignore <addr> <n>      don't pause on the next n hits of a breakpoint
+delete <addr>     d    remove a breakpoint
+breakpoints       bl   list breakpoints and their hit counts
+watch <addr>[-<end>] [rwx] [log]
+                  wa   pause on (or only log) accesses, writes by default
+unwatch <n>       uw   remove a watchpoint
+watchpoints       wl   list watchpoints
+registers         r    show the registers
+memory <addr> [n] x    dump n bytes of memory (64 by default)
+poke <addr> <b>..      write bytes to memory
+disassemble [addr] l   disassemble around an address (PC by default)
+stack             bt   show the call stack
+quit              q    exit the emulator
+help              h    show this help";
+
+/// What the frontend does after a debugger command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Wait for the next command.
+    Stay,
+    /// Leave the debugger and run the program.
+    Resume,
+    Quit,
+}
+
+/// Text printed by a command, and what to do next.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub output: String,
+    pub action: Action,
+}
+
+impl Response {
+    fn stay(output: String) -> Self {
+        Self {
+            output,
+            action: Action::Stay,
+        }
+    }
+}
+
+/// Parses a hexadecimal number, with or without a `0x` prefix.
+pub fn parse_hex(value: &str) -> Result<usize, String> {
+    usize::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("Couldn't parse value '{}' as a hexadecimal number.", value))
+}
+
+/// Parses an address within `memory_size` bytes of memory.
+fn parse_address(value: &str, memory_size: usize) -> Result<usize, String> {
+    match parse_hex(value)? {
+        address if address < memory_size => Ok(address),
+        address => Err(format!("Address 0x{:x} is out of memory.", address)),
+    }
+}
+
+/// Breakpoint, or tracepoint when `trace` is set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Breakpoint {
+    /// Only hit when the condition is true.
+    pub condition: Option<Expression>,
+    /// Hits left before pausing.
+    pub ignore: u64,
+    /// Logs the CPU state on hits instead of pausing.
+    pub trace: bool,
+    /// Hits so far, ignored ones included.
+    pub hits: u64,
+}
+
+impl Breakpoint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", if self.trace { "trace" } else { "break" })?;
+        if let Some(ref condition) = self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        write!(f, " hits={}", self.hits)?;
+        if self.ignore > 0 {
+            write!(f, " ignore={}", self.ignore)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the arguments of `break` and `tracepoint`: an address, then
+/// optionally `if` and a condition.
+fn parse_breakpoint(
+    args: &[&str],
+    trace: bool,
+    memory_size: usize,
+) -> Result<(u16, Breakpoint), String> {
+    let address = parse_address(args.first().ok_or("Missing address.")?, memory_size)?;
+    let mut breakpoint = Breakpoint::new();
+    breakpoint.trace = trace;
+
+    match args.get(1) {
+        Some(&"if") => breakpoint.condition = Some(args[2..].join(" ").parse()?),
+        Some(word) => return Err(format!("Expected 'if', found '{}'.", word)),
+        None => {}
+    }
+
+    Ok((address as u16, breakpoint))
+}
+
+/// Runs the CPU instruction by instruction, stopping at breakpoints and
+/// watchpoints, and interprets the commands of the `--debug` REPL.
+///
+/// Frames are counted in instructions so that the timers keep being
+/// decremented once every `instructions_per_frame` instructions, however the
+/// program is stepped through.
+pub struct Debugger {
+    instructions_per_frame: usize,
+    breakpoints: BTreeMap<u16, Breakpoint>,
+    paused: bool,
+    // Instructions already run in the current frame.
+    frame_position: usize,
+    // Tracepoint and watchpoint lines not reported yet.
+    log: Vec<String>,
+    // Hit of a pausing watchpoint by the last instruction.
+    stopped_by: Option<WatchHit>,
+    history: History,
+}
+
+impl Debugger {
+    /// Creates a paused debugger.
+    pub fn new(instructions_per_frame: usize) -> Self {
+        Self {
+            instructions_per_frame,
+            breakpoints: BTreeMap::new(),
+            paused: true,
+            frame_position: 0,
+            log: Vec::new(),
+            stopped_by: None,
+            history: History::new(
+                (instructions_per_frame * FRAME_RATE as usize * CHECKPOINT_INTERVAL).max(1) as u64,
+                CHECKPOINTS,
+            ),
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true
+    }
+
+    pub fn breakpoints(&self) -> &BTreeMap<u16, Breakpoint> {
+        &self.breakpoints
+    }
+
+    /// Sets an unconditional breakpoint, keeping the one already there.
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.entry(address).or_default();
+    }
+
+    pub fn set_breakpoint(&mut self, address: u16, breakpoint: Breakpoint) {
+        self.breakpoints.insert(address, breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    pub fn remove_all_breakpoints(&mut self) {
+        self.breakpoints.clear()
+    }
+
+    fn breakpoint_mut(&mut self, address: &str) -> Result<&mut Breakpoint, String> {
+        let address = parse_hex(address)?;
+
+        self.breakpoints
+            .get_mut(&(address as u16))
+            .ok_or_else(|| format!("No breakpoint at 0x{:04x}.", address))
+    }
+
+    /// Lines logged by tracepoints and watchpoints since the last call.
+    pub fn report(&mut self) -> String {
+        self.log.drain(..).collect()
+    }
+
+    /// Hit of the watchpoint the debugger paused on, if any.
+    pub fn stopped_by(&self) -> Option<WatchHit> {
+        self.stopped_by
+    }
+
+    /// Forgets the history, which can't be replayed any more after the
+    /// state of the CPU was changed by hand.
+    pub fn reset_history(&mut self) {
+        self.history.clear()
+    }
+
+    /// Executes one instruction, recording it in the history.
+    pub fn tick(&mut self, cpu: &mut CPU, input: &State) -> Result<(), CpuFault> {
+        self.history.record(cpu, self.frame_position, input);
+
+        self.stopped_by = None;
+        let result = self.run_instruction(cpu, input);
+        for hit in cpu.watchpoints_mut().take_hits() {
+            // Pausing watchpoints on execution are hit by `run_frame()`,
+            // before the instruction runs.
+            if !hit.log && hit.access == Access::Execute {
+                continue;
+            }
+            if !hit.log && self.stopped_by.is_none() {
+                self.stopped_by = Some(hit);
+            }
+            self.log.push(format!("Watchpoint: {}\n", hit));
+        }
+
+        result
+    }
+
+    /// Executes one instruction, ending the frame after the last one.
+    fn run_instruction(&mut self, cpu: &mut CPU, input: &State) -> Result<(), CpuFault> {
+        if self.frame_position == 0 {
+            cpu.vblank();
+        }
+
+        let result = cpu.tick(input);
+
+        self.frame_position += 1;
+        if self.frame_position >= self.instructions_per_frame {
+            self.end_frame(cpu);
+        }
+
+        result
+    }
+
+    fn end_frame(&mut self, cpu: &mut CPU) {
+        cpu.decrement_delay();
+        cpu.decrement_sound();
+        self.frame_position = 0;
+    }
+
+    /// Leaves the pause, first stepping off a breakpoint at PC, which would
+    /// otherwise stop the program right away.
+    /// Stays paused if that instruction hits a watchpoint.
+    pub fn resume(&mut self, cpu: &mut CPU, input: &State) -> Result<(), CpuFault> {
+        self.tick(cpu, input)?;
+        self.paused = self.stopped_by.is_some();
+
+        Ok(())
+    }
+
+    /// Runs the rest of the current frame, pausing on a breakpoint, a
+    /// watchpoint or a fault.
+    pub fn run_frame(&mut self, cpu: &mut CPU, input: &State) -> Result<(), CpuFault> {
+        loop {
+            if cpu.halted() {
+                self.end_frame(cpu);
+                return Ok(());
+            }
+            if self.breaks_at(cpu) {
+                self.paused = true;
+                return Ok(());
+            }
+            if let Some(hit) = self.execute_hit(cpu) {
+                self.log.push(format!("Watchpoint: {}\n", hit));
+                self.stopped_by = Some(hit);
+                self.paused = true;
+                return Ok(());
+            }
+
+            if let Err(fault) = self.tick(cpu, input) {
+                self.paused = true;
+                return Err(fault);
+            }
+            if self.stopped_by.is_some() {
+                self.paused = true;
+                return Ok(());
+            }
+            if self.frame_position == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Goes back `count` instructions.
+    pub fn reverse_step(&mut self, cpu: &mut CPU, count: u64) -> Result<(), String> {
+        let cycles = cpu.cycles().saturating_sub(count);
+        match self.history.start() {
+            Some(start) if start <= cycles && count <= cpu.cycles() => {
+                self.travel(cpu, cycles);
+                Ok(())
+            }
+            Some(_) => Err("Reached the start of the history.".to_string()),
+            None => Err("No history to go back to.".to_string()),
+        }
+    }
+
+    /// Goes back to the last pause on a breakpoint or a watchpoint, or to
+    /// the start of the history, returning whether a pause was found.
+    ///
+    /// Tracepoints, hit counts and ignore counts are left alone.
+    pub fn reverse_continue(&mut self, cpu: &mut CPU) -> Result<bool, String> {
+        let start = self.history.start().ok_or("No history to go back to.")?;
+
+        // Searches the stretches between checkpoints, latest first.
+        let mut end = cpu.cycles();
+        while let Some(checkpoint) = self.history.checkpoint_before(end) {
+            let from = checkpoint.cycles();
+            if let Some((cycles, hit)) = self.last_pause(cpu, from, end) {
+                self.travel(cpu, cycles);
+                self.stopped_by = hit;
+                return Ok(true);
+            }
+            end = from;
+        }
+
+        self.travel(cpu, start);
+        Ok(false)
+    }
+
+    /// Restores the checkpoint at or before `cycles`, with the instructions
+    /// and watchpoint hits since left out of the trace and the log.
+    fn restore(&mut self, cpu: &mut CPU, cycles: u64) {
+        if let Some(checkpoint) = self.history.checkpoint(cycles) {
+            cpu.restore(&checkpoint.snapshot);
+            self.frame_position = checkpoint.frame_position;
+        }
+    }
+
+    /// Takes the CPU back to the instruction at `cycles`, re-executing it
+    /// from a checkpoint with the recorded inputs.
+    fn travel(&mut self, cpu: &mut CPU, cycles: u64) {
+        let trace = cpu.take_trace();
+        self.restore(cpu, cycles);
+
+        while cpu.cycles() < cycles && !cpu.halted() {
+            let input = self.history.input(cpu.cycles());
+            if self.run_instruction(cpu, &input).is_err() {
+                break;
+            }
+        }
+
+        cpu.watchpoints_mut().take_hits();
+        cpu.set_trace(trace);
+        self.history.truncate(cpu.cycles());
+        self.stopped_by = None;
+    }
+
+    /// Cycle count of the last pause between `from` and `end`, excluded,
+    /// and the watchpoint hit causing it.
+    fn last_pause(
+        &mut self,
+        cpu: &mut CPU,
+        from: u64,
+        end: u64,
+    ) -> Option<(u64, Option<WatchHit>)> {
+        let trace = cpu.take_trace();
+        let mut pause = None;
+        self.restore(cpu, from);
+
+        while cpu.cycles() < end && !cpu.halted() {
+            if let Some(hit) = self.execute_hit(cpu) {
+                pause = Some((cpu.cycles(), Some(hit)));
+            } else if self.pauses_at(cpu) {
+                pause = Some((cpu.cycles(), None));
+            }
+
+            let input = self.history.input(cpu.cycles());
+            let result = self.run_instruction(cpu, &input);
+            let hit = cpu
+                .watchpoints_mut()
+                .take_hits()
+                .into_iter()
+                .find(|hit| !hit.log && hit.access != Access::Execute);
+            if hit.is_some() && cpu.cycles() < end {
+                pause = Some((cpu.cycles(), hit));
+            }
+            if result.is_err() {
+                break;
+            }
+        }
+
+        cpu.set_trace(trace);
+        pause
+    }
+
+    /// Whether a breakpoint at PC pauses, regardless of hit counts.
+    fn pauses_at(&self, cpu: &CPU) -> bool {
+        self.breakpoints.get(&cpu.pc()).is_some_and(|breakpoint| {
+            !breakpoint.trace
+                && match breakpoint.condition {
+                    Some(ref condition) => condition.is_true(cpu),
+                    None => true,
+                }
+        })
+    }
+
+    /// Whether the breakpoint at PC, if any, pauses. Counts its hits and
+    /// logs the hits of tracepoints.
+    fn breaks_at(&mut self, cpu: &CPU) -> bool {
+        let breakpoint = match self.breakpoints.get_mut(&cpu.pc()) {
+            Some(breakpoint) => breakpoint,
+            None => return false,
+        };
+        if let Some(ref condition) = breakpoint.condition {
+            if !condition.is_true(cpu) {
+                return false;
+            }
+        }
+
+        breakpoint.hits += 1;
+        if breakpoint.trace {
+            let instruction = Instruction::fetch(cpu.ram(), cpu.pc() as usize);
+            self.log.push(format!(
+                "Tracepoint: {}\n",
+                format_line(cpu, &instruction, false)
+            ));
+            return false;
+        }
+        if breakpoint.ignore > 0 {
+            breakpoint.ignore -= 1;
+            return false;
+        }
+
+        true
+    }
+
+    /// Hit of a pausing watchpoint on the instruction at PC.
+    fn execute_hit(&self, cpu: &CPU) -> Option<WatchHit> {
+        let pc = cpu.pc();
+        let length = Instruction::fetch(cpu.ram(), pc as usize).length();
+
+        (0..length)
+            .map(|offset| pc.wrapping_add(offset) as usize % cpu.memory_size())
+            .find(|&address| cpu.watchpoints().breaks_on_execute(address))
+            .map(|address| WatchHit {
+                access: Access::Execute,
+                address,
+                value: cpu.ram()[address],
+                pc,
+                log: false,
+            })
+    }
+
+    /// Interprets one REPL command.
+    pub fn execute(
+        &mut self,
+        command: &str,
+        cpu: &mut CPU,
+        input: &State,
+    ) -> Result<Response, String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let (name, args) = match words.split_first() {
+            Some((name, args)) => (*name, args),
+            None => return Ok(Response::stay(String::new())),
+        };
+
+        match name {
+            "step" | "s" => {
+                let count = match args.first() {
+                    Some(count) => count
+                        .parse::<usize>()
+                        .map_err(|_| format!("Couldn't parse value '{}' as a count.", count))?,
+                    None => 1,
+                };
+                for _ in 0..count {
+                    self.tick(cpu, input).map_err(|fault| fault.to_string())?;
+                    if self.stopped_by.is_some() {
+                        break;
+                    }
+                }
+                Ok(Response::stay(self.report() + &self.current(cpu)))
+            }
+            "next" | "n" => {
+                self.next(cpu, input)?;
+                Ok(Response::stay(self.report() + &self.current(cpu)))
+            }
+            "continue" | "c" => {
+                self.resume(cpu, input).map_err(|fault| fault.to_string())?;
+                if self.paused {
+                    return Ok(Response::stay(self.report() + &self.current(cpu)));
+                }
+                Ok(Response {
+                    output: self.report(),
+                    action: Action::Resume,
+                })
+            }
+            "reverse-step" | "rs" => {
+                let count = match args.first() {
+                    Some(count) => count
+                        .parse::<u64>()
+                        .map_err(|_| format!("Couldn't parse value '{}' as a count.", count))?,
+                    None => 1,
+                };
+                self.reverse_step(cpu, count)?;
+                Ok(Response::stay(self.current(cpu)))
+            }
+            "reverse-continue" | "rc" => {
+                let output = if self.reverse_continue(cpu)? {
+                    String::new()
+                } else {
+                    "Reached the start of the history.\n".to_string()
+                };
+                Ok(Response::stay(output + &self.current(cpu)))
+            }
+            "break" | "b" => {
+                let (address, breakpoint) = parse_breakpoint(args, false, cpu.memory_size())?;
+                self.set_breakpoint(address, breakpoint);
+                Ok(Response::stay(format!("Breakpoint at 0x{:04x}\n", address)))
+            }
+            "tracepoint" | "tp" => {
+                let (address, breakpoint) = parse_breakpoint(args, true, cpu.memory_size())?;
+                self.set_breakpoint(address, breakpoint);
+                Ok(Response::stay(format!("Tracepoint at 0x{:04x}\n", address)))
+            }
+            "condition" | "cond" => {
+                let breakpoint = self.breakpoint_mut(args.first().ok_or("Missing address.")?)?;
+                breakpoint.condition = match args.len() {
+                    1 => None,
+                    _ => Some(args[1..].join(" ").parse()?),
+                };
+                Ok(Response::stay(String::new()))
+            }
+            "ignore" => {
+                let count = args.get(1).ok_or("Missing count.")?;
+                let count = count
+                    .parse::<u64>()
+                    .map_err(|_| format!("Couldn't parse value '{}' as a count.", count))?;
+                self.breakpoint_mut(args[0])?.ignore = count;
+                Ok(Response::stay(String::new()))
+            }
+            "delete" | "d" => {
+                let address = parse_hex(args.first().ok_or("Missing address.")?)?;
+                if !self.remove_breakpoint(address as u16) {
+                    return Err(format!("No breakpoint at 0x{:04x}.", address));
+                }
+                Ok(Response::stay(String::new()))
+            }
+            "breakpoints" | "bl" => Ok(Response::stay(
+                self.breakpoints
+                    .iter()
+                    .map(|(address, breakpoint)| format!("0x{:04x} {}\n", address, breakpoint))
+                    .collect(),
+            )),
+            "watch" | "wa" => {
+                let watchpoint: Watchpoint = args.join(" ").parse()?;
+                if *watchpoint.range.end() >= cpu.memory_size() {
+                    return Err(format!(
+                        "Address 0x{:x} is out of memory.",
+                        watchpoint.range.end()
+                    ));
+                }
+                let output = format!(
+                    "Watchpoint {}: {}\n",
+                    cpu.watchpoints().list().len(),
+                    watchpoint
+                );
+                cpu.watchpoints_mut().add(watchpoint);
+                Ok(Response::stay(output))
+            }
+            "unwatch" | "uw" => {
+                let index = args.first().ok_or("Missing watchpoint number.")?;
+                let index = index
+                    .parse::<usize>()
+                    .map_err(|_| format!("Couldn't parse value '{}' as a number.", index))?;
+                if cpu.watchpoints_mut().remove(index).is_none() {
+                    return Err(format!("No watchpoint {}.", index));
+                }
+                Ok(Response::stay(String::new()))
+            }
+            "watchpoints" | "wl" => Ok(Response::stay(
+                cpu.watchpoints()
+                    .list()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, watchpoint)| format!("{}: {}\n", index, watchpoint))
+                    .collect(),
+            )),
+            "registers" | "r" => Ok(Response::stay(registers(cpu))),
+            "memory" | "x" => {
+                let address =
+                    parse_address(args.first().ok_or("Missing address.")?, cpu.memory_size())?;
+                let length = match args.get(1) {
+                    Some(length) => parse_hex(length)?,
+                    None => DUMP_LENGTH,
+                };
+                let end = address.saturating_add(length).min(cpu.memory_size());
+                Ok(Response::stay(dump(&cpu.ram()[address..end], address)))
+            }
+            "poke" => {
+                let address =
+                    parse_address(args.first().ok_or("Missing address.")?, cpu.memory_size())?;
+                let bytes = args[1..]
+                    .iter()
+                    .map(|byte| match parse_hex(byte)? {
+                        byte if byte <= 0xFF => Ok(byte as u8),
+                        _ => Err(format!("Value '{}' doesn't fit in a byte.", byte)),
+                    })
+                    .collect::<Result<Vec<u8>, String>>()?;
+                if bytes.is_empty() || address + bytes.len() > cpu.memory_size() {
+                    return Err("Expected bytes to write within memory.".to_string());
+                }
+                cpu.poke(address, &bytes);
+                self.reset_history();
+                Ok(Response::stay(String::new()))
+            }
+            "disassemble" | "l" => {
+                let address = match args.first() {
+                    Some(address) => parse_address(address, cpu.memory_size())?,
+                    None => cpu.pc() as usize,
+                };
+                Ok(Response::stay(self.disassemble(cpu, address)))
+            }
+            "stack" | "bt" => Ok(Response::stay(call_stack(cpu))),
+            "quit" | "q" => Ok(Response {
+                output: String::new(),
+                action: Action::Quit,
+            }),
+            "help" | "h" => Ok(Response::stay(format!("{}\n", HELP))),
+            _ => Err(format!("Unknown command '{}', try 'help'.", name)),
+        }
+    }
+
+    fn next(&mut self, cpu: &mut CPU, input: &State) -> Result<(), String> {
+        let instruction = Instruction::fetch(cpu.ram(), cpu.pc() as usize);
+        let returned = cpu.pc().wrapping_add(instruction.length());
+        let depth = cpu.sp();
+
+        self.tick(cpu, input).map_err(|fault| fault.to_string())?;
+        if instruction.opcode != Opcode::CALL_ADDR {
+            return Ok(());
+        }
+
+        for _ in 0..NEXT_LIMIT {
+            if cpu.halted() || (cpu.pc() == returned && cpu.sp() == depth) {
+                return Ok(());
+            }
+            if self.breaks_at(cpu) || self.execute_hit(cpu).is_some() {
+                return Ok(());
+            }
+            self.tick(cpu, input).map_err(|fault| fault.to_string())?;
+            if self.stopped_by.is_some() {
+                return Ok(());
+            }
+        }
+
+        Err(format!(
+            "Call didn't return after {} instructions.",
+            NEXT_LIMIT
+        ))
+    }
+
+    /// Line describing the instruction at PC, shown after stepping.
+    pub fn current(&self, cpu: &CPU) -> String {
+        let instruction = Instruction::fetch(cpu.ram(), cpu.pc() as usize);
+
+        format!("0x{:04x}: {}\n", cpu.pc(), instruction)
+    }
+
+    fn disassemble(&self, cpu: &CPU, around: usize) -> String {
+        let mut output = String::new();
+
+        for (address, instruction) in inspector::disassemble(cpu.ram(), around, DISASSEMBLY_CONTEXT)
+        {
+            let marker = if address == cpu.pc() as usize {
+                "=>"
+            } else if self.breakpoints.contains_key(&(address as u16)) {
+                " *"
+            } else {
+                "  "
+            };
+
+            output.push_str(&format!(
+                "{} 0x{:04x}: {:04x}  {}\n",
+                marker, address, instruction.raw, instruction
+            ));
+        }
+
+        output
+    }
+}
+
+fn registers(cpu: &CPU) -> String {
+    let mut output = String::new();
+
+    for (x, v) in cpu.v().iter().enumerate() {
+        output.push_str(&format!("V{:X}=0x{:02x}", x, v));
+        output.push_str(if x % 8 == 7 { "\n" } else { " " });
+    }
+    output.push_str(&format!(
+        "I=0x{:04x} PC=0x{:04x} SP=0x{:x} DT=0x{:02x} ST=0x{:02x}\n",
+        cpu.i(),
+        cpu.pc(),
+        cpu.sp(),
+        cpu.dt(),
+        cpu.st()
+    ));
+
+    output
+}
+
+/// Hexdump of `bytes`, each line prefixed by its address.
+fn dump(bytes: &[u8], address: usize) -> String {
+    // The last line of `hexdump_iter` is a summary, left out.
+    hexdump::hexdump_iter(bytes)
+        .take(bytes.len().div_ceil(16))
+        .enumerate()
+        .map(|(line, text)| format!("0x{:04x}: {}\n", address + line * 16, text))
+        .collect()
+}
+
+/// Current instruction then return addresses, innermost first.
+fn call_stack(cpu: &CPU) -> String {
+    let mut output = format!("#0 0x{:04x}\n", cpu.pc());
+
+    for (frame, &address) in cpu.stack()[..cpu.sp()].iter().rev().enumerate() {
+        output.push_str(&format!("#{} 0x{:04x}\n", frame + 1, address));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod debugger_test {
+    use super::*;
+    use crate::MEMORY_SIZE;
+
+    fn setup(program: &[u8]) -> (CPU, Debugger, State) {
+        (CPU::init(program), Debugger::new(4), State::new())
+    }
+
+    // CALL 0x206; JP 0x202; (0x204) DW; ADD V0, 1; ADD V0, 1; RET
+    const CALLER: [u8; 12] = [
+        0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x70, 0x01, 0x70, 0x01, 0x00, 0xEE,
+    ];
+
+    #[test]
+    fn test_step() {
+        let (mut cpu, mut debugger, state) = setup(&CALLER);
+
+        let response = debugger.execute("step 2", &mut cpu, &state).unwrap();
+        assert_eq!(response.output, "0x0208: ADD V0, 0x01\n");
+        assert_eq!(response.action, Action::Stay);
+        assert_eq!(cpu.v()[0], 1);
+    }
+
+    #[test]
+    fn test_next_over_call() {
+        let (mut cpu, mut debugger, state) = setup(&CALLER);
+
+        debugger.execute("n", &mut cpu, &state).unwrap();
+        assert_eq!(cpu.pc(), 0x202);
+        assert_eq!(cpu.v()[0], 2);
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let (mut cpu, mut debugger, state) = setup(&CALLER);
+        debugger.execute("b 208", &mut cpu, &state).unwrap();
+
+        let response = debugger.execute("c", &mut cpu, &state).unwrap();
+        assert_eq!(response.action, Action::Resume);
+        assert!(!debugger.paused());
+
+        debugger.run_frame(&mut cpu, &state).unwrap();
+        assert!(debugger.paused());
+        assert_eq!(cpu.pc(), 0x208);
+
+        let stack = debugger.execute("bt", &mut cpu, &state).unwrap();
+        assert_eq!(stack.output, "#0 0x0208\n#1 0x0202\n");
+    }
+
+    #[test]
+    fn test_timers_follow_frames() {
+        // LD V0, 5; LD DT, V0; JP 0x204
+        let (mut cpu, mut debugger, state) = setup(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04]);
+
+        debugger.execute("s 2", &mut cpu, &state).unwrap();
+        assert_eq!(cpu.dt(), 5);
+        debugger.execute("s 2", &mut cpu, &state).unwrap();
+        assert_eq!(cpu.dt(), 4);
+    }
+
+    #[test]
+    fn test_memory_and_poke() {
+        let (mut cpu, mut debugger, state) = setup(&CALLER);
+
+        debugger
+            .execute("poke 0x300 de ad", &mut cpu, &state)
+            .unwrap();
+        assert_eq!(&cpu.ram()[0x300..0x302], &[0xde, 0xad]);
+
+        let dump = debugger.execute("x 300 20", &mut cpu, &state).unwrap();
+        assert_eq!(dump.output.lines().count(), 2);
+        assert!(dump.output.starts_with("0x0300: |dead0000 "));
+
+        let dump = debugger
+            .execute("x ff0 ffffffffffffffff", &mut cpu, &state)
+            .unwrap();
+        assert_eq!(dump.output.lines().count(), 1);
+
+        assert!(debugger.execute("poke ffff 1 2", &mut cpu, &state).is_err());
+        assert!(debugger.execute("poke 300 100", &mut cpu, &state).is_err());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let (mut cpu, mut debugger, state) = setup(&CALLER);
+        debugger.add_breakpoint(0x202);
+
+        let listing = debugger.execute("l", &mut cpu, &state).unwrap().output;
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[5], "=> 0x0200: 2206  CALL 0x206");
+        assert_eq!(lines[6], " * 0x0202: 1202  JP 0x202");
+    }
+
+    #[test]
+    fn test_conditions() {
+        // ADD V0, 1; JP 0x200
+        let (mut cpu, mut debugger, state) = setup(&[0x70, 0x01, 0x12, 0x00]);
+        debugger
+            .execute("b 202 if V0 == 3", &mut cpu, &state)
+            .unwrap();
+        debugger
+            .execute("tp 200 if v0 & 1", &mut cpu, &state)
+            .unwrap();
+        debugger.execute("c", &mut cpu, &state).unwrap();
+
+        while !debugger.paused() {
+            debugger.run_frame(&mut cpu, &state).unwrap();
+        }
+        assert_eq!(cpu.v()[0], 3);
+        assert_eq!(
+            debugger.report(),
+            "Tracepoint: 0000000002 0200 7001 v=01000000000000000000000000000000 i=0000 sp=00 dt=00 st=00 ADD V0, 0x01\n"
+        );
+
+        debugger.execute("ignore 202 2", &mut cpu, &state).unwrap();
+        debugger
+            .execute("cond 202 V0 > 3", &mut cpu, &state)
+            .unwrap();
+        debugger.execute("c", &mut cpu, &state).unwrap();
+        while !debugger.paused() {
+            debugger.run_frame(&mut cpu, &state).unwrap();
+        }
+        assert_eq!(cpu.v()[0], 6);
+
+        let list = debugger.execute("bl", &mut cpu, &state).unwrap().output;
+        assert_eq!(
+            list,
+            "0x0200 trace if v0 & 1 hits=3\n0x0202 break if V0 > 3 hits=4\n"
+        );
+        assert!(debugger
+            .execute("b 202 if V0 ==", &mut cpu, &state)
+            .is_err());
+        assert!(debugger.execute("b 202 V0", &mut cpu, &state).is_err());
+        assert!(debugger.execute("ignore 204 1", &mut cpu, &state).is_err());
+    }
+
+    #[test]
+    fn test_reverse() {
+        // LD I, 0x300; ADD V0, 1; LD [I], V0; JP 0x202
+        let (mut cpu, mut debugger, state) =
+            setup(&[0xA3, 0x00, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x02]);
+        assert!(debugger.execute("rs", &mut cpu, &state).is_err());
+
+        debugger.execute("s 9", &mut cpu, &state).unwrap();
+        let snapshot = cpu.snapshot();
+        assert_eq!(cpu.ram()[0x300], 3);
+
+        let response = debugger.execute("rs 2", &mut cpu, &state).unwrap();
+        assert_eq!(response.output, "0x0202: ADD V0, 0x01\n");
+        assert_eq!(cpu.cycles(), 7);
+        assert_eq!(cpu.v()[0], 2);
+        assert_eq!(cpu.ram()[0x300], 2);
+        assert!(debugger.execute("rs 8", &mut cpu, &state).is_err());
+
+        debugger.execute("watch 300", &mut cpu, &state).unwrap();
+        debugger.execute("rc", &mut cpu, &state).unwrap();
+        assert_eq!(cpu.cycles(), 6);
+        assert_eq!(cpu.pc(), 0x206);
+        assert_eq!(debugger.stopped_by().unwrap().value, 2);
+
+        debugger
+            .execute("b 202 if V0 == 0", &mut cpu, &state)
+            .unwrap();
+        debugger.execute("rc", &mut cpu, &state).unwrap();
+        assert_eq!(cpu.cycles(), 3);
+        debugger.execute("rc", &mut cpu, &state).unwrap();
+        assert_eq!(cpu.cycles(), 1);
+        assert!(debugger.stopped_by().is_none());
+        let response = debugger.execute("rc", &mut cpu, &state).unwrap();
+        assert!(response.output.starts_with("Reached the start"));
+        assert_eq!(cpu.cycles(), 0);
+        assert_eq!(cpu.ram()[0x300], 0);
+
+        debugger.execute("d 202", &mut cpu, &state).unwrap();
+        debugger.execute("uw 0", &mut cpu, &state).unwrap();
+        debugger.execute("s 9", &mut cpu, &state).unwrap();
+        assert_eq!(cpu.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        // LD I, 0x300; LD V0, 0x07; LD [I], V0; LD V1, [I]; JP 0x208
+        let (mut cpu, mut debugger, state) =
+            setup(&[0xA3, 0x00, 0x60, 0x07, 0xF0, 0x55, 0xF1, 0x65, 0x12, 0x08]);
+        debugger.execute("watch 300 rw", &mut cpu, &state).unwrap();
+        debugger.execute("wa 208 x", &mut cpu, &state).unwrap();
+
+        let response = debugger.execute("s 10", &mut cpu, &state).unwrap();
+        assert_eq!(
+            response.output,
+            "Watchpoint: write 0x0300 = 0x07 at 0x0204\n0x0206: LD V1, [I]\n"
+        );
+
+        let response = debugger.execute("c", &mut cpu, &state).unwrap();
+        assert_eq!(response.action, Action::Stay);
+        assert!(response.output.starts_with("Watchpoint: read 0x0300"));
+
+        debugger.execute("c", &mut cpu, &state).unwrap();
+        assert!(!debugger.paused());
+        debugger.run_frame(&mut cpu, &state).unwrap();
+        assert!(debugger.paused());
+        assert_eq!(cpu.pc(), 0x208);
+        assert_eq!(debugger.stopped_by().unwrap().access, Access::Execute);
+
+        debugger.execute("uw 0", &mut cpu, &state).unwrap();
+        let list = debugger.execute("wl", &mut cpu, &state).unwrap();
+        assert_eq!(list.output, "0: 0x0208 x\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        let (mut cpu, mut debugger, state) = setup(&CALLER);
+
+        assert!(debugger.execute("frobnicate", &mut cpu, &state).is_err());
+        assert!(debugger.execute("b", &mut cpu, &state).is_err());
+        assert!(debugger.execute("b 10000", &mut cpu, &state).is_err());
+        assert!(debugger.execute("d 300", &mut cpu, &state).is_err());
+
+        // 4 KiB of memory until the profile says otherwise.
+        assert!(debugger.execute("b 2000", &mut cpu, &state).is_err());
+        assert!(debugger.execute("watch 2000", &mut cpu, &state).is_err());
+        assert!(debugger
+            .execute("watch f00-1000", &mut cpu, &state)
+            .is_err());
+        assert!(debugger.execute("x 1000", &mut cpu, &state).is_err());
+        cpu.set_memory_size(MEMORY_SIZE).unwrap();
+        debugger.execute("b 2000", &mut cpu, &state).unwrap();
+        debugger.execute("watch 2000", &mut cpu, &state).unwrap();
+        assert_eq!(
+            debugger.execute("q", &mut cpu, &state).unwrap().action,
+            Action::Quit
+        );
+    }
+}
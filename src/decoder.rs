@@ -1,11 +1,16 @@
-#[allow(dead_code)]
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Args {
-    x: usize,
-    y: usize,
-    n: usize,
-    kk: u8,
-    nnn: u16,
-    nibbles: (u8, u8, u8, u8),
+    pub x: usize,
+    pub y: usize,
+    pub n: usize,
+    pub kk: u8,
+    pub nnn: u16,
+    /// Second word of the XO-CHIP `F000 NNNN` long load.
+    pub nnnn: u16,
+    pub nibbles: (u8, u8, u8, u8),
 }
 
 impl Args {
@@ -18,26 +23,35 @@ impl Args {
         );
 
         Self {
-            nibbles: nibbles,
+            nibbles,
             x: nibbles.1 as usize,
             y: nibbles.2 as usize,
             n: nibbles.3 as usize,
             kk: (instruction & 0x00FF) as u8,
             nnn: instruction & 0x0FFF,
+            nnnn: 0,
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum Opcode {
+    SCD_CONST,
     CLS,
     RET,
+    SCR,
+    SCL,
+    EXIT,
+    LOW,
+    HIGH,
     JP_ADDR,
     CALL_ADDR,
     SE_REG_CONST,
     SNE_REG_CONST,
     SE_REG_REG,
+    SAVE_REG_REG,
+    LOAD_REG_REG,
     LD_REG_CONST,
     ADD_REG_CONST,
     LD_REG_REG,
@@ -51,6 +65,7 @@ pub enum Opcode {
     SHL_REG,
     SNE_REG_REG,
     LD_I_ADDR,
+    LD_I_LONG,
     JP_V0_ADDR,
     RND_REG_CONST,
     DRW_REG_REG_CONST,
@@ -62,25 +77,224 @@ pub enum Opcode {
     LD_SOUND_REG,
     ADD_I_REG,
     LD_FONT_CHAR,
+    LD_BIG_FONT_CHAR,
     LD_BCD,
     LD_IND_REG,
     LD_REG_IND,
+    LD_FLAGS_REG,
+    LD_REG_FLAGS,
+    PLANE_CONST,
+    AUDIO,
+    PITCH_REG,
     UNKNOWN,
 }
 
+/// Broad kind of an opcode, used to filter traces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpcodeClass {
+    /// Jumps, calls, returns and register comparisons.
+    Flow,
+    /// Register loads and arithmetic, including `RND`.
+    Arithmetic,
+    /// I register and memory transfers.
+    Memory,
+    Display,
+    Input,
+    Timer,
+    Sound,
+    Unknown,
+}
+
+pub const CLASSES: [&str; 8] = [
+    "flow",
+    "arithmetic",
+    "memory",
+    "display",
+    "input",
+    "timer",
+    "sound",
+    "unknown",
+];
+
+impl FromStr for OpcodeClass {
+    type Err = String;
+
+    fn from_str(class: &str) -> Result<Self, Self::Err> {
+        match class {
+            "flow" => Ok(OpcodeClass::Flow),
+            "arithmetic" => Ok(OpcodeClass::Arithmetic),
+            "memory" => Ok(OpcodeClass::Memory),
+            "display" => Ok(OpcodeClass::Display),
+            "input" => Ok(OpcodeClass::Input),
+            "timer" => Ok(OpcodeClass::Timer),
+            "sound" => Ok(OpcodeClass::Sound),
+            "unknown" => Ok(OpcodeClass::Unknown),
+            _ => Err(format!(
+                "Unknown opcode class '{}', expected one of: {}.",
+                class,
+                CLASSES.join(", ")
+            )),
+        }
+    }
+}
+
+impl Opcode {
+    pub fn class(self) -> OpcodeClass {
+        use Opcode::*;
+
+        match self {
+            RET | EXIT | JP_ADDR | CALL_ADDR | JP_V0_ADDR | SE_REG_CONST | SNE_REG_CONST
+            | SE_REG_REG | SNE_REG_REG => OpcodeClass::Flow,
+            LD_REG_CONST | ADD_REG_CONST | LD_REG_REG | OR_REG_REG | AND_REG_REG | XOR_REG_REG
+            | ADD_REG_REG | SUB_REG_REG | SHR_REG | SUBN_REG_REG | SHL_REG | RND_REG_CONST => {
+                OpcodeClass::Arithmetic
+            }
+            SAVE_REG_REG | LOAD_REG_REG | LD_I_ADDR | LD_I_LONG | ADD_I_REG | LD_FONT_CHAR
+            | LD_BIG_FONT_CHAR | LD_BCD | LD_IND_REG | LD_REG_IND | LD_FLAGS_REG | LD_REG_FLAGS => {
+                OpcodeClass::Memory
+            }
+            SCD_CONST | CLS | SCR | SCL | LOW | HIGH | DRW_REG_REG_CONST | PLANE_CONST => {
+                OpcodeClass::Display
+            }
+            SKP_REG | SKNP_REG | LD_REG_KEY => OpcodeClass::Input,
+            LD_REG_DELAY | LD_DELAY_REG => OpcodeClass::Timer,
+            LD_SOUND_REG | AUDIO | PITCH_REG => OpcodeClass::Sound,
+            UNKNOWN => OpcodeClass::Unknown,
+        }
+    }
+}
+
+/// A decoded instruction: the raw word, its opcode and its operands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Instruction {
+    pub raw: u16,
+    pub opcode: Opcode,
+    pub args: Args,
+}
+
+impl Instruction {
+    pub fn decode(raw: u16) -> Self {
+        let (opcode, args) = decode_instruction(raw);
+
+        Self { raw, opcode, args }
+    }
+
+    /// Decodes the instruction stored at `address` in `memory`, including
+    /// the operand word of `F000 NNNN`. Bytes past the end of `memory` read
+    /// as zero.
+    pub fn fetch(memory: &[u8], address: usize) -> Self {
+        let word = |at: usize| {
+            let byte = |at: usize| memory.get(at).copied().unwrap_or(0) as u16;
+            byte(at) << 8 | byte(at + 1)
+        };
+
+        let mut instruction = Self::decode(word(address));
+        if instruction.opcode == Opcode::LD_I_LONG {
+            instruction.args.nnnn = word(address + 2);
+        }
+
+        instruction
+    }
+
+    /// Size of the instruction in memory, in bytes.
+    pub fn length(&self) -> u16 {
+        match self.opcode {
+            Opcode::LD_I_LONG => 4,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Args {
+            x,
+            y,
+            n,
+            kk,
+            nnn,
+            nnnn,
+            ..
+        } = self.args;
+
+        use Opcode::*;
+
+        match self.opcode {
+            SCD_CONST => write!(f, "SCD 0x{:x}", n),
+            CLS => write!(f, "CLS"),
+            RET => write!(f, "RET"),
+            SCR => write!(f, "SCR"),
+            SCL => write!(f, "SCL"),
+            EXIT => write!(f, "EXIT"),
+            LOW => write!(f, "LOW"),
+            HIGH => write!(f, "HIGH"),
+            JP_ADDR => write!(f, "JP 0x{:03x}", nnn),
+            CALL_ADDR => write!(f, "CALL 0x{:03x}", nnn),
+            SE_REG_CONST => write!(f, "SE V{:X}, 0x{:02x}", x, kk),
+            SNE_REG_CONST => write!(f, "SNE V{:X}, 0x{:02x}", x, kk),
+            SE_REG_REG => write!(f, "SE V{:X}, V{:X}", x, y),
+            SAVE_REG_REG => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            LOAD_REG_REG => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            LD_REG_CONST => write!(f, "LD V{:X}, 0x{:02x}", x, kk),
+            ADD_REG_CONST => write!(f, "ADD V{:X}, 0x{:02x}", x, kk),
+            LD_REG_REG => write!(f, "LD V{:X}, V{:X}", x, y),
+            OR_REG_REG => write!(f, "OR V{:X}, V{:X}", x, y),
+            AND_REG_REG => write!(f, "AND V{:X}, V{:X}", x, y),
+            XOR_REG_REG => write!(f, "XOR V{:X}, V{:X}", x, y),
+            ADD_REG_REG => write!(f, "ADD V{:X}, V{:X}", x, y),
+            SUB_REG_REG => write!(f, "SUB V{:X}, V{:X}", x, y),
+            SHR_REG => write!(f, "SHR V{:X}, V{:X}", x, y),
+            SUBN_REG_REG => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            SHL_REG => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SNE_REG_REG => write!(f, "SNE V{:X}, V{:X}", x, y),
+            LD_I_ADDR => write!(f, "LD I, 0x{:03x}", nnn),
+            LD_I_LONG => write!(f, "LD I, 0x{:04x}", nnnn),
+            JP_V0_ADDR => write!(f, "JP V0, 0x{:03x}", nnn),
+            RND_REG_CONST => write!(f, "RND V{:X}, 0x{:02x}", x, kk),
+            DRW_REG_REG_CONST => write!(f, "DRW V{:X}, V{:X}, 0x{:x}", x, y, n),
+            SKP_REG => write!(f, "SKP V{:X}", x),
+            SKNP_REG => write!(f, "SKNP V{:X}", x),
+            LD_REG_DELAY => write!(f, "LD V{:X}, DT", x),
+            LD_REG_KEY => write!(f, "LD V{:X}, K", x),
+            LD_DELAY_REG => write!(f, "LD DT, V{:X}", x),
+            LD_SOUND_REG => write!(f, "LD ST, V{:X}", x),
+            ADD_I_REG => write!(f, "ADD I, V{:X}", x),
+            LD_FONT_CHAR => write!(f, "LD F, V{:X}", x),
+            LD_BIG_FONT_CHAR => write!(f, "LD HF, V{:X}", x),
+            LD_BCD => write!(f, "LD B, V{:X}", x),
+            LD_IND_REG => write!(f, "LD [I], V{:X}", x),
+            LD_REG_IND => write!(f, "LD V{:X}, [I]", x),
+            LD_FLAGS_REG => write!(f, "LD R, V{:X}", x),
+            LD_REG_FLAGS => write!(f, "LD V{:X}, R", x),
+            PLANE_CONST => write!(f, "PLANE 0x{:x}", x),
+            AUDIO => write!(f, "AUDIO"),
+            PITCH_REG => write!(f, "PITCH V{:X}", x),
+            UNKNOWN => write!(f, "DW 0x{:04x}", self.raw),
+        }
+    }
+}
+
 pub fn decode_instruction(instruction: u16) -> (Opcode, Args) {
     let args = Args::new(instruction);
 
     use Opcode::*;
 
     let opcode = match args.nibbles {
+        (0x0, 0x0, 0xC, _) => SCD_CONST,
         (0x0, 0x0, 0xE, 0x0) => CLS,
         (0x0, 0x0, 0xE, 0xE) => RET,
+        (0x0, 0x0, 0xF, 0xB) => SCR,
+        (0x0, 0x0, 0xF, 0xC) => SCL,
+        (0x0, 0x0, 0xF, 0xD) => EXIT,
+        (0x0, 0x0, 0xF, 0xE) => LOW,
+        (0x0, 0x0, 0xF, 0xF) => HIGH,
         (0x1, _, _, _) => JP_ADDR,
         (0x2, _, _, _) => CALL_ADDR,
         (0x3, _, _, _) => SE_REG_CONST,
         (0x4, _, _, _) => SNE_REG_CONST,
         (0x5, _, _, 0x0) => SE_REG_REG,
+        (0x5, _, _, 0x2) => SAVE_REG_REG,
+        (0x5, _, _, 0x3) => LOAD_REG_REG,
         (0x6, _, _, _) => LD_REG_CONST,
         (0x7, _, _, _) => ADD_REG_CONST,
         (0x8, _, _, 0x0) => LD_REG_REG,
@@ -99,15 +313,22 @@ pub fn decode_instruction(instruction: u16) -> (Opcode, Args) {
         (0xD, _, _, _) => DRW_REG_REG_CONST,
         (0xE, _, 0x9, 0xE) => SKP_REG,
         (0xE, _, 0xA, 0x1) => SKNP_REG,
+        (0xF, 0x0, 0x0, 0x0) => LD_I_LONG,
+        (0xF, _, 0x0, 0x1) => PLANE_CONST,
+        (0xF, 0x0, 0x0, 0x2) => AUDIO,
         (0xF, _, 0x0, 0x7) => LD_REG_DELAY,
         (0xF, _, 0x0, 0xA) => LD_REG_KEY,
         (0xF, _, 0x1, 0x5) => LD_DELAY_REG,
         (0xF, _, 0x1, 0x8) => LD_SOUND_REG,
         (0xF, _, 0x1, 0xE) => ADD_I_REG,
         (0xF, _, 0x2, 0x9) => LD_FONT_CHAR,
+        (0xF, _, 0x3, 0x0) => LD_BIG_FONT_CHAR,
         (0xF, _, 0x3, 0x3) => LD_BCD,
         (0xF, _, 0x5, 0x5) => LD_IND_REG,
         (0xF, _, 0x6, 0x5) => LD_REG_IND,
+        (0xF, _, 0x7, 0x5) => LD_FLAGS_REG,
+        (0xF, _, 0x8, 0x5) => LD_REG_FLAGS,
+        (0xF, _, 0x3, 0xA) => PITCH_REG,
         (_, _, _, _) => UNKNOWN,
     };
 
@@ -130,6 +351,14 @@ mod decoder_test {
         assert_eq!(args.nnn, 0x234);
     }
 
+    #[test]
+    fn test_scd_const() {
+        let (opcode, args) = decode_instruction(0x00C4);
+
+        assert_eq!(opcode, Opcode::SCD_CONST);
+        assert_eq!(args.n, 4);
+    }
+
     #[test]
     fn test_clear_screen() {
         let (opcode, _) = decode_instruction(0x00E0);
@@ -144,6 +373,41 @@ mod decoder_test {
         assert_eq!(opcode, Opcode::RET);
     }
 
+    #[test]
+    fn test_scr() {
+        let (opcode, _) = decode_instruction(0x00FB);
+
+        assert_eq!(opcode, Opcode::SCR);
+    }
+
+    #[test]
+    fn test_scl() {
+        let (opcode, _) = decode_instruction(0x00FC);
+
+        assert_eq!(opcode, Opcode::SCL);
+    }
+
+    #[test]
+    fn test_exit() {
+        let (opcode, _) = decode_instruction(0x00FD);
+
+        assert_eq!(opcode, Opcode::EXIT);
+    }
+
+    #[test]
+    fn test_low() {
+        let (opcode, _) = decode_instruction(0x00FE);
+
+        assert_eq!(opcode, Opcode::LOW);
+    }
+
+    #[test]
+    fn test_high() {
+        let (opcode, _) = decode_instruction(0x00FF);
+
+        assert_eq!(opcode, Opcode::HIGH);
+    }
+
     #[test]
     fn test_jp_addr() {
         let (opcode, _) = decode_instruction(0x1234);
@@ -179,6 +443,20 @@ mod decoder_test {
         assert_eq!(opcode, Opcode::SE_REG_REG);
     }
 
+    #[test]
+    fn test_save_reg_reg() {
+        let (opcode, _) = decode_instruction(0x5122);
+
+        assert_eq!(opcode, Opcode::SAVE_REG_REG);
+    }
+
+    #[test]
+    fn test_load_reg_reg() {
+        let (opcode, _) = decode_instruction(0x5123);
+
+        assert_eq!(opcode, Opcode::LOAD_REG_REG);
+    }
+
     #[test]
     fn test_ld_reg_const() {
         let (opcode, _) = decode_instruction(0x6123);
@@ -270,6 +548,13 @@ mod decoder_test {
         assert_eq!(opcode, Opcode::LD_I_ADDR);
     }
 
+    #[test]
+    fn test_ld_i_long() {
+        let (opcode, _) = decode_instruction(0xF000);
+
+        assert_eq!(opcode, Opcode::LD_I_LONG);
+    }
+
     #[test]
     fn test_jp_v0_addr() {
         let (opcode, _) = decode_instruction(0xB123);
@@ -347,6 +632,13 @@ mod decoder_test {
         assert_eq!(opcode, Opcode::LD_FONT_CHAR);
     }
 
+    #[test]
+    fn test_ld_big_font_char() {
+        let (opcode, _) = decode_instruction(0xF130);
+
+        assert_eq!(opcode, Opcode::LD_BIG_FONT_CHAR);
+    }
+
     #[test]
     fn test_ld_bcd() {
         let (opcode, _) = decode_instruction(0xF133);
@@ -368,10 +660,119 @@ mod decoder_test {
         assert_eq!(opcode, Opcode::LD_REG_IND);
     }
 
+    #[test]
+    fn test_ld_flags_reg() {
+        let (opcode, _) = decode_instruction(0xF175);
+
+        assert_eq!(opcode, Opcode::LD_FLAGS_REG);
+    }
+
+    #[test]
+    fn test_ld_reg_flags() {
+        let (opcode, _) = decode_instruction(0xF185);
+
+        assert_eq!(opcode, Opcode::LD_REG_FLAGS);
+    }
+
+    #[test]
+    fn test_plane_const() {
+        let (opcode, args) = decode_instruction(0xF301);
+
+        assert_eq!(opcode, Opcode::PLANE_CONST);
+        assert_eq!(args.x, 3);
+    }
+
+    #[test]
+    fn test_audio() {
+        let (opcode, _) = decode_instruction(0xF002);
+
+        assert_eq!(opcode, Opcode::AUDIO);
+    }
+
+    #[test]
+    fn test_pitch_reg() {
+        let (opcode, _) = decode_instruction(0xF13A);
+
+        assert_eq!(opcode, Opcode::PITCH_REG);
+    }
+
     #[test]
     fn test_unknown() {
         let (opcode, _) = decode_instruction(0x0000);
 
         assert_eq!(opcode, Opcode::UNKNOWN);
     }
+
+    #[test]
+    fn test_decode() {
+        let instruction = Instruction::decode(0xD125);
+
+        assert_eq!(instruction.raw, 0xD125);
+        assert_eq!(instruction.opcode, Opcode::DRW_REG_REG_CONST);
+        assert_eq!(instruction.args, Args::new(0xD125));
+    }
+
+    #[test]
+    fn test_fetch() {
+        let memory = [0x00, 0xE0, 0xF0, 0x00, 0x12, 0x34, 0xF0];
+
+        let instruction = Instruction::fetch(&memory, 0);
+        assert_eq!(instruction.opcode, Opcode::CLS);
+        assert_eq!(instruction.length(), 2);
+
+        let instruction = Instruction::fetch(&memory, 2);
+        assert_eq!(instruction.opcode, Opcode::LD_I_LONG);
+        assert_eq!(instruction.args.nnnn, 0x1234);
+        assert_eq!(instruction.length(), 4);
+        assert_eq!(instruction.to_string(), "LD I, 0x1234");
+
+        let instruction = Instruction::fetch(&memory, 6);
+        assert_eq!(instruction.raw, 0xF000);
+        assert_eq!(instruction.args.nnnn, 0x0000);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Instruction::decode(0x00E0).to_string(), "CLS");
+        assert_eq!(Instruction::decode(0x1234).to_string(), "JP 0x234");
+        assert_eq!(Instruction::decode(0x3A0F).to_string(), "SE VA, 0x0f");
+        assert_eq!(Instruction::decode(0x8AB4).to_string(), "ADD VA, VB");
+        assert_eq!(Instruction::decode(0xD125).to_string(), "DRW V1, V2, 0x5");
+        assert_eq!(Instruction::decode(0xF355).to_string(), "LD [I], V3");
+        assert_eq!(Instruction::decode(0x0000).to_string(), "DW 0x0000");
+    }
+
+    #[test]
+    fn test_class() {
+        assert_eq!(
+            Instruction::decode(0x2234).opcode.class(),
+            OpcodeClass::Flow
+        );
+        assert_eq!(
+            Instruction::decode(0x8AB5).opcode.class(),
+            OpcodeClass::Arithmetic
+        );
+        assert_eq!(
+            Instruction::decode(0xF355).opcode.class(),
+            OpcodeClass::Memory
+        );
+        assert_eq!(
+            Instruction::decode(0xD125).opcode.class(),
+            OpcodeClass::Display
+        );
+        assert_eq!(
+            Instruction::decode(0xE19E).opcode.class(),
+            OpcodeClass::Input
+        );
+        assert_eq!(
+            Instruction::decode(0xF015).opcode.class(),
+            OpcodeClass::Timer
+        );
+        assert_eq!(
+            Instruction::decode(0xF018).opcode.class(),
+            OpcodeClass::Sound
+        );
+        assert_eq!("display".parse::<OpcodeClass>(), Ok(OpcodeClass::Display));
+        assert!("io".parse::<OpcodeClass>().is_err());
+    }
 }
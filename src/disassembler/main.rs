@@ -2,15 +2,39 @@ extern crate clap;
 
 use clap::{App, Arg, ArgMatches};
 
+use crust::decoder::Instruction;
 use crust::rom::{load_rom, Reader};
 
+const ROM_START: usize = 0x200;
+
 fn run(matches: &ArgMatches) -> Result<(), String> {
     let rom_path = matches.value_of("ROM").unwrap();
     let rom = match load_rom(rom_path) {
         Ok(rom) => rom,
         Err(error) => return Err(error.to_string()),
     };
-    let _reader = Reader::new(&rom);
+
+    let bytes: Vec<u8> = Reader::new(&rom).collect();
+    // The buffer is zero-padded up to ROM_SIZE, skip the padding.
+    let length = bytes
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |pos| pos + 1);
+
+    let mut address = 0;
+    while address < length {
+        let instruction = Instruction::fetch(&bytes, address);
+
+        println!(
+            "{:04x}: {:04x}: {}",
+            ROM_START + address,
+            instruction.raw,
+            instruction
+        );
+
+        address += instruction.length() as usize;
+    }
+
     Ok(())
 }
 
@@ -7,24 +7,27 @@ use crate::context::Context;
 use crate::UPSCALE;
 use crust::{HEIGHT, WIDTH};
 
-const FACTOR: i32 = UPSCALE as i32;
-
 pub enum DisplayColor {
     Black,
     White,
+    LightGray,
+    DarkGray,
 }
 
 impl DisplayColor {
-    fn to_sdl_color(self) -> sdl2::pixels::Color {
+    fn into_sdl_color(self) -> sdl2::pixels::Color {
         match self {
-            DisplayColor::Black => return Color::RGB(0, 0, 0),
-            DisplayColor::White => return Color::RGB(255, 255, 255),
+            DisplayColor::Black => Color::RGB(0, 0, 0),
+            DisplayColor::White => Color::RGB(255, 255, 255),
+            DisplayColor::LightGray => Color::RGB(170, 170, 170),
+            DisplayColor::DarkGray => Color::RGB(85, 85, 85),
         }
     }
 }
 
 pub struct Display {
     canvas: sdl2::render::WindowCanvas,
+    factor: i32,
 }
 
 impl Display {
@@ -44,26 +47,55 @@ impl Display {
 
         let canvas = window.into_canvas().build().unwrap();
 
-        Self { canvas: canvas }
+        Self {
+            canvas,
+            factor: UPSCALE as i32,
+        }
+    }
+
+    /// Position right of the window, for another one to be opened there.
+    pub fn position_right(&self) -> (i32, i32) {
+        let window = self.canvas.window();
+        let (x, y) = window.position();
+
+        (x + window.size().0 as i32, y)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.canvas.window().size()
+    }
+
+    /// Scales pixels so that a `width` x `height` screen fills the window.
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        let (window_width, window_height) = self.canvas.window().size();
+
+        self.factor = (window_width as usize / width).min(window_height as usize / height) as i32;
     }
 
     pub fn set_color(&mut self, color: DisplayColor) {
         self.canvas
-            .set_draw_color(DisplayColor::to_sdl_color(color))
+            .set_draw_color(DisplayColor::into_sdl_color(color))
     }
 
     pub fn draw_pixel(&mut self, x: i32, y: i32, color: DisplayColor) {
         self.set_color(color);
         self.canvas
             .fill_rect(Rect::new(
-                x * FACTOR,
-                y * FACTOR,
-                FACTOR as u32,
-                FACTOR as u32,
+                x * self.factor,
+                y * self.factor,
+                self.factor as u32,
+                self.factor as u32,
             ))
             .expect("Could not draw a frame");
     }
 
+    pub fn fill_rects(&mut self, rects: &[Rect], color: DisplayColor) {
+        self.set_color(color);
+        self.canvas
+            .fill_rects(rects)
+            .expect("Could not draw a frame");
+    }
+
     pub fn reset_screen(&mut self, color: DisplayColor) {
         self.set_color(color);
         self.canvas.clear();
@@ -0,0 +1,366 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cpu::CPU;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Register {
+    V(usize),
+    I,
+    PC,
+    SP,
+    DT,
+    ST,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Subtract,
+    Not,
+    // Unary `-`.
+    Negate,
+    Complement,
+}
+
+// Binary operators from the lowest precedence to the highest.
+const PRECEDENCE: [&[Operator]; 7] = [
+    &[Operator::Or],
+    &[Operator::And],
+    &[
+        Operator::Equal,
+        Operator::NotEqual,
+        Operator::Less,
+        Operator::LessEqual,
+        Operator::Greater,
+        Operator::GreaterEqual,
+    ],
+    &[Operator::BitOr],
+    &[Operator::BitXor],
+    &[Operator::BitAnd],
+    &[Operator::Add, Operator::Subtract],
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Register(Register),
+    Operator(Operator),
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    Memory(Box<Node>),
+    Unary(Operator, Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    const OPERATORS: [(&str, Operator); 15] = [
+        ("||", Operator::Or),
+        ("&&", Operator::And),
+        ("==", Operator::Equal),
+        ("!=", Operator::NotEqual),
+        ("<=", Operator::LessEqual),
+        (">=", Operator::GreaterEqual),
+        ("<", Operator::Less),
+        (">", Operator::Greater),
+        ("|", Operator::BitOr),
+        ("^", Operator::BitXor),
+        ("&", Operator::BitAnd),
+        ("+", Operator::Add),
+        ("-", Operator::Subtract),
+        ("!", Operator::Not),
+        ("~", Operator::Complement),
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_ascii_alphanumeric() {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            tokens.push(operand(&rest[..length])?);
+            length
+        } else if let Some(token) = [
+            ('(', Token::Open),
+            (')', Token::Close),
+            ('[', Token::OpenBracket),
+            (']', Token::CloseBracket),
+        ]
+        .iter()
+        .find(|(bracket, _)| *bracket == c)
+        {
+            tokens.push(token.1.clone());
+            1
+        } else {
+            let (symbol, operator) = OPERATORS
+                .iter()
+                .find(|(symbol, _)| rest.starts_with(symbol))
+                .ok_or_else(|| format!("Unexpected character '{}'.", c))?;
+            tokens.push(Token::Operator(*operator));
+            symbol.len()
+        };
+
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+fn operand(word: &str) -> Result<Token, String> {
+    let upper = word.to_ascii_uppercase();
+    let register = match upper.as_str() {
+        "I" => Some(Register::I),
+        "PC" => Some(Register::PC),
+        "SP" => Some(Register::SP),
+        "DT" => Some(Register::DT),
+        "ST" => Some(Register::ST),
+        _ if upper.len() == 2 && upper.starts_with('V') => {
+            usize::from_str_radix(&upper[1..], 16).ok().map(Register::V)
+        }
+        _ => None,
+    };
+    if let Some(register) = register {
+        return Ok(Token::Register(register));
+    }
+
+    let number = match upper.strip_prefix("0X") {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => upper.parse::<i64>(),
+    };
+    number
+        .map(Token::Number)
+        .map_err(|_| format!("Couldn't parse value '{}' as a number or register.", word))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, name: &str) -> Result<(), String> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            _ => Err(format!("Expected '{}'.", name)),
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Node, String> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(&Token::Operator(operator)) = self.peek() {
+            if !PRECEDENCE[level].contains(&operator) {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = Node::Binary(operator, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Operator(Operator::Not)) => {
+                Ok(Node::Unary(Operator::Not, Box::new(self.unary()?)))
+            }
+            Some(Token::Operator(Operator::Subtract)) => {
+                Ok(Node::Unary(Operator::Negate, Box::new(self.unary()?)))
+            }
+            Some(Token::Operator(Operator::Complement)) => {
+                Ok(Node::Unary(Operator::Complement, Box::new(self.unary()?)))
+            }
+            Some(Token::Number(number)) => Ok(Node::Number(number)),
+            Some(Token::Register(register)) => Ok(Node::Register(register)),
+            Some(Token::Open) => {
+                let node = self.binary(0)?;
+                self.expect(Token::Close, ")")?;
+                Ok(node)
+            }
+            Some(Token::OpenBracket) => {
+                let node = self.binary(0)?;
+                self.expect(Token::CloseBracket, "]")?;
+                Ok(Node::Memory(Box::new(node)))
+            }
+            _ => Err("Expected a number, a register or a memory access.".to_string()),
+        }
+    }
+}
+
+fn evaluate(node: &Node, cpu: &CPU) -> i64 {
+    match node {
+        Node::Number(number) => *number,
+        Node::Register(register) => match register {
+            Register::V(x) => cpu.v()[*x] as i64,
+            Register::I => cpu.i() as i64,
+            Register::PC => cpu.pc() as i64,
+            Register::SP => cpu.sp() as i64,
+            Register::DT => cpu.dt() as i64,
+            Register::ST => cpu.st() as i64,
+        },
+        // Reads past the end of memory give 0.
+        Node::Memory(address) => usize::try_from(evaluate(address, cpu))
+            .ok()
+            .and_then(|address| cpu.ram().get(address))
+            .map_or(0, |&byte| byte as i64),
+        Node::Unary(operator, operand) => {
+            let value = evaluate(operand, cpu);
+            match operator {
+                Operator::Not => (value == 0) as i64,
+                Operator::Complement => !value,
+                _ => value.wrapping_neg(),
+            }
+        }
+        Node::Binary(operator, left, right) => {
+            let left = evaluate(left, cpu);
+            // `&&` and `||` short-circuit.
+            match operator {
+                Operator::And if left == 0 => return 0,
+                Operator::Or if left != 0 => return 1,
+                _ => {}
+            }
+            let right = evaluate(right, cpu);
+
+            match operator {
+                Operator::Or | Operator::And => (right != 0) as i64,
+                Operator::Equal => (left == right) as i64,
+                Operator::NotEqual => (left != right) as i64,
+                Operator::Less => (left < right) as i64,
+                Operator::LessEqual => (left <= right) as i64,
+                Operator::Greater => (left > right) as i64,
+                Operator::GreaterEqual => (left >= right) as i64,
+                Operator::BitOr => left | right,
+                Operator::BitXor => left ^ right,
+                Operator::BitAnd => left & right,
+                Operator::Add => left.wrapping_add(right),
+                _ => left.wrapping_sub(right),
+            }
+        }
+    }
+}
+
+/// Parsed expression, displayed as written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn evaluate(&self, cpu: &CPU) -> i64 {
+        evaluate(&self.root, cpu)
+    }
+
+    pub fn is_true(&self, cpu: &CPU) -> bool {
+        self.evaluate(cpu) != 0
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(expression)?,
+            position: 0,
+        };
+        let root = parser.binary(0)?;
+        if parser.peek().is_some() {
+            return Err(format!("Unexpected trailing input in '{}'.", expression));
+        }
+
+        Ok(Self {
+            source: expression.trim().to_string(),
+            root,
+        })
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod expression_test {
+    use super::*;
+
+    fn evaluate(expression: &str, cpu: &CPU) -> i64 {
+        expression.parse::<Expression>().unwrap().evaluate(cpu)
+    }
+
+    #[test]
+    fn test_operands() {
+        let mut cpu = CPU::new();
+        cpu.set_v(3, 0x10);
+        cpu.set_i(0x2f0);
+        cpu.poke(0x2f0, &[0x42]);
+
+        assert_eq!(evaluate("v3", &cpu), 0x10);
+        assert_eq!(evaluate("I", &cpu), 0x2f0);
+        assert_eq!(evaluate("[0x2F0]", &cpu), 0x42);
+        assert_eq!(evaluate("[I]", &cpu), 0x42);
+        assert_eq!(evaluate("[0x10000]", &cpu), 0);
+        assert_eq!(evaluate("16", &cpu), 16);
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut cpu = CPU::new();
+        cpu.set_v(3, 0x10);
+        cpu.set_i(0x301);
+
+        assert_eq!(evaluate("V3 == 0x10 && I > 0x300", &cpu), 1);
+        assert_eq!(evaluate("V3 == 0x10 && I > 0x301", &cpu), 0);
+        assert_eq!(evaluate("V0 || V3", &cpu), 1);
+        assert_eq!(evaluate("1 + 2 == 3", &cpu), 1);
+        assert_eq!(evaluate("V3 & 0x18 | 1", &cpu), 0x11);
+        assert_eq!(evaluate("-(2 - 3)", &cpu), 1);
+        assert_eq!(evaluate("!V0 && ~0 == -1", &cpu), 1);
+        assert_eq!(evaluate("1 <= 1 && 2 >= 3", &cpu), 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        for expression in &["", "V3 ==", "(1", "[1", "VG", "1 2", "V3 = 1", "$"] {
+            assert!(expression.parse::<Expression>().is_err(), "{}", expression);
+        }
+    }
+}
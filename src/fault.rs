@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Error raised by an instruction the CPU cannot execute.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CpuFault {
+    /// `CALL` with all the stack entries in use.
+    StackOverflow { pc: u16 },
+    /// `RET` with an empty stack.
+    StackUnderflow { pc: u16 },
+    /// Memory access past the end of the RAM.
+    OutOfBounds { pc: u16, address: usize },
+    /// Instruction matching no known opcode.
+    UnknownOpcode { pc: u16, opcode: u16 },
+}
+
+impl CpuFault {
+    /// Address of the faulting instruction.
+    pub fn pc(&self) -> u16 {
+        match *self {
+            CpuFault::StackOverflow { pc }
+            | CpuFault::StackUnderflow { pc }
+            | CpuFault::OutOfBounds { pc, .. }
+            | CpuFault::UnknownOpcode { pc, .. } => pc,
+        }
+    }
+}
+
+impl fmt::Display for CpuFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CpuFault::StackOverflow { pc } => write!(f, "stack overflow at 0x{:04x}", pc),
+            CpuFault::StackUnderflow { pc } => write!(f, "stack underflow at 0x{:04x}", pc),
+            CpuFault::OutOfBounds { pc, address } => write!(
+                f,
+                "out of bounds memory access to 0x{:x} at 0x{:04x}",
+                address, pc
+            ),
+            CpuFault::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode 0x{:04x} at 0x{:04x}", opcode, pc)
+            }
+        }
+    }
+}
+
+impl Error for CpuFault {}
+
+/// What the CPU does when a fault occurs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FaultAction {
+    /// Stop, leaving PC on the faulting instruction.
+    Halt,
+    /// Skip the faulting instruction, or the faulting access.
+    Ignore,
+    /// Wrap the stack pointer or the address around. Faults with nothing to
+    /// wrap are ignored.
+    Wrap,
+}
+
+pub const ACTIONS: [&str; 3] = ["halt", "ignore", "wrap"];
+
+impl FromStr for FaultAction {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "halt" => Ok(FaultAction::Halt),
+            "ignore" => Ok(FaultAction::Ignore),
+            "wrap" => Ok(FaultAction::Wrap),
+            _ => Err(format!(
+                "Unknown fault action '{}', expected one of: {}.",
+                action,
+                ACTIONS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Action taken for each kind of fault.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaultPolicy {
+    pub stack_overflow: FaultAction,
+    pub stack_underflow: FaultAction,
+    pub out_of_bounds: FaultAction,
+    pub unknown_opcode: FaultAction,
+}
+
+impl FaultPolicy {
+    /// Applies `action` to every fault.
+    pub fn uniform(action: FaultAction) -> Self {
+        Self {
+            stack_overflow: action,
+            stack_underflow: action,
+            out_of_bounds: action,
+            unknown_opcode: action,
+        }
+    }
+
+    pub fn action(&self, fault: &CpuFault) -> FaultAction {
+        match fault {
+            CpuFault::StackOverflow { .. } => self.stack_overflow,
+            CpuFault::StackUnderflow { .. } => self.stack_underflow,
+            CpuFault::OutOfBounds { .. } => self.out_of_bounds,
+            CpuFault::UnknownOpcode { .. } => self.unknown_opcode,
+        }
+    }
+}
+
+impl Default for FaultPolicy {
+    /// Halts on every fault except unknown opcodes, which are skipped.
+    fn default() -> Self {
+        Self {
+            unknown_opcode: FaultAction::Ignore,
+            ..Self::uniform(FaultAction::Halt)
+        }
+    }
+}
+
+#[cfg(test)]
+mod fault_test {
+    use super::*;
+
+    #[test]
+    fn test_fault_pc() {
+        let fault = CpuFault::OutOfBounds {
+            pc: 0x234,
+            address: 0x10000,
+        };
+
+        assert_eq!(fault.pc(), 0x234);
+        assert_eq!(
+            fault.to_string(),
+            "out of bounds memory access to 0x10000 at 0x0234"
+        );
+    }
+
+    #[test]
+    fn test_policy_action() {
+        let policy = FaultPolicy::default();
+
+        assert_eq!(
+            policy.action(&CpuFault::StackOverflow { pc: 0 }),
+            FaultAction::Halt
+        );
+        assert_eq!(
+            policy.action(&CpuFault::UnknownOpcode { pc: 0, opcode: 0 }),
+            FaultAction::Ignore
+        );
+    }
+
+    #[test]
+    fn test_action_from_str() {
+        assert_eq!("wrap".parse::<FaultAction>(), Ok(FaultAction::Wrap));
+        assert!("abort".parse::<FaultAction>().is_err());
+    }
+}
@@ -0,0 +1,770 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use crate::cpu::{CPU, STACK_SIZE};
+use crate::debugger::{Action, Debugger};
+use crate::fault::CpuFault;
+use crate::keypad::State;
+use crate::watch::{Access, Watchpoint};
+
+const INTERRUPT: u8 = 0x03;
+const PACKET_SIZE: usize = 0x4000;
+
+/// Stop reply when going backwards reaches the start of the history.
+const HISTORY_START: &str = "T05replaylog:begin;";
+
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+/// (name, bits) of the registers, in GDB's numbering.
+const REGISTERS: [(&str, usize); 21] = [
+    ("v0", 8),
+    ("v1", 8),
+    ("v2", 8),
+    ("v3", 8),
+    ("v4", 8),
+    ("v5", 8),
+    ("v6", 8),
+    ("v7", 8),
+    ("v8", 8),
+    ("v9", 8),
+    ("va", 8),
+    ("vb", 8),
+    ("vc", 8),
+    ("vd", 8),
+    ("ve", 8),
+    ("vf", 8),
+    ("i", 16),
+    ("pc", 16),
+    ("sp", 8),
+    ("dt", 8),
+    ("st", 8),
+];
+
+/// Stream GDB is connected through.
+pub trait Connection: Read + Write {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_hex(hex: &str) -> Option<usize> {
+    usize::from_str_radix(hex, 16).ok()
+}
+
+/// `addr,length` arguments of memory packets, within `memory_size` bytes.
+fn parse_range(range: &str, memory_size: usize) -> Option<(usize, usize)> {
+    let mut parts = range.splitn(2, ',');
+    let address = parse_hex(parts.next()?)?;
+    let length = parse_hex(parts.next()?)?;
+
+    if address.checked_add(length)? > memory_size {
+        return None;
+    }
+    Some((address, length))
+}
+
+fn target_xml() -> String {
+    let registers: String = REGISTERS
+        .iter()
+        .enumerate()
+        .map(|(number, (name, bits))| {
+            format!(
+                "<reg name=\"{}\" bitsize=\"{}\" regnum=\"{}\"/>",
+                name, bits, number
+            )
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\">\
+         <target><feature name=\"org.crust.chip8\">{}</feature></target>",
+        registers
+    )
+}
+
+fn read_register(cpu: &CPU, number: usize) -> Option<Vec<u8>> {
+    Some(match number {
+        0..=15 => vec![cpu.v()[number]],
+        16 => (cpu.i() as u16).to_be_bytes().to_vec(),
+        17 => cpu.pc().to_be_bytes().to_vec(),
+        18 => vec![cpu.sp() as u8],
+        19 => vec![cpu.dt()],
+        20 => vec![cpu.st()],
+        _ => return None,
+    })
+}
+
+/// Value of register `number` in `bytes`, when it fits the register.
+fn register_value(number: usize, bytes: &[u8]) -> Option<u16> {
+    let bits = REGISTERS.get(number)?.1;
+    if bytes.len() != bits / 8 {
+        return None;
+    }
+
+    match bytes.iter().fold(0, |word, &byte| word << 8 | byte as u16) {
+        word if number == 18 && word as usize > STACK_SIZE => None,
+        word => Some(word),
+    }
+}
+
+fn write_register(cpu: &mut CPU, number: usize, bytes: &[u8]) -> Option<()> {
+    let word = register_value(number, bytes)?;
+
+    match number {
+        0..=15 => cpu.set_v(number, word as u8),
+        16 => cpu.set_i(word as usize),
+        17 => cpu.set_pc(word),
+        18 => cpu.set_sp(word as usize),
+        19 => cpu.set_dt(word as u8),
+        _ => cpu.set_st(word as u8),
+    }
+    Some(())
+}
+
+fn signal(fault: Option<CpuFault>) -> u8 {
+    match fault {
+        Some(CpuFault::UnknownOpcode { .. }) => SIGILL,
+        Some(_) => SIGSEGV,
+        None => SIGTRAP,
+    }
+}
+
+/// Stop reply telling why the program is paused.
+fn stop_reply(debugger: &Debugger, cpu: &CPU) -> String {
+    let kind = match debugger.stopped_by() {
+        Some(hit) if cpu.fault().is_none() => match hit.access {
+            Access::Read => "rwatch",
+            Access::Write => "watch",
+            Access::Execute => return format!("S{:02x}", SIGTRAP),
+        },
+        _ => return format!("S{:02x}", signal(cpu.fault())),
+    };
+
+    let address = debugger.stopped_by().map_or(0, |hit| hit.address);
+    format!("T{:02x}{}:{:x};", SIGTRAP, kind, address)
+}
+
+/// Watchpoint of a `Z2` to `Z4` packet, from its type, address and length.
+fn watchpoint(kind: &str, address: usize, length: usize, cpu: &CPU) -> Option<Watchpoint> {
+    let end = address.checked_add(length.max(1) - 1)?;
+    if end >= cpu.memory_size() {
+        return None;
+    }
+
+    let mut watchpoint = Watchpoint::new(address..=end);
+    watchpoint.read = kind != "2";
+    watchpoint.write = kind != "3";
+    Some(watchpoint)
+}
+
+/// Serves one GDB connection, driving the CPU through a `Debugger`.
+///
+/// While the debugger is paused the frontend calls `serve()` for each
+/// packet; while the program runs it calls `poll_interrupt()` every frame,
+/// and `report_stop()` once the debugger pauses.
+pub struct GdbStub {
+    connection: Box<dyn Connection>,
+    buffer: Vec<u8>,
+    // Whether GDB waits for a stop reply to a continue.
+    running: bool,
+    detached: bool,
+}
+
+impl GdbStub {
+    pub fn new(connection: Box<dyn Connection>) -> Self {
+        Self {
+            connection,
+            buffer: Vec::new(),
+            running: false,
+            detached: false,
+        }
+    }
+
+    /// Whether GDB detached or went away, the program running on its own.
+    pub fn detached(&self) -> bool {
+        self.detached
+    }
+
+    fn detach(&mut self, debugger: &mut Debugger, cpu: &mut CPU, input: &State) {
+        self.detached = true;
+        debugger.remove_all_breakpoints();
+        cpu.watchpoints_mut().clear();
+        let _ = debugger.resume(cpu, input);
+    }
+
+    fn read_byte(&mut self) -> io::Result<u8> {
+        if self.buffer.is_empty() {
+            let mut chunk = [0; 256];
+            let length = self.connection.read(&mut chunk)?;
+            if length == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.buffer.extend_from_slice(&chunk[..length]);
+        }
+
+        Ok(self.buffer.remove(0))
+    }
+
+    /// Reads the next packet, acknowledging it. Returns `None` for an
+    /// interrupt request.
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.read_byte()? {
+                b'$' => {}
+                INTERRUPT => return Ok(None),
+                _ => continue,
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    b'#' => break,
+                    byte => data.push(byte),
+                }
+            }
+            let sum = [self.read_byte()?, self.read_byte()?];
+
+            let expected = std::str::from_utf8(&sum)
+                .ok()
+                .and_then(|sum| u8::from_str_radix(sum, 16).ok());
+            if expected == Some(checksum(&data)) {
+                self.connection.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+            self.connection.write_all(b"-")?;
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        write!(
+            self.connection,
+            "${}#{:02x}",
+            data,
+            checksum(data.as_bytes())
+        )?;
+        self.connection.flush()
+    }
+
+    /// Waits for a packet from GDB and handles it.
+    pub fn serve(
+        &mut self,
+        debugger: &mut Debugger,
+        cpu: &mut CPU,
+        input: &State,
+    ) -> io::Result<Action> {
+        let packet = match self.read_packet() {
+            Ok(Some(packet)) => packet,
+            Ok(None) => return Ok(Action::Stay),
+            Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                self.detach(debugger, cpu, input);
+                return Ok(Action::Resume);
+            }
+            Err(error) => return Err(error),
+        };
+
+        let (reply, action) = self.handle(&packet, debugger, cpu, input);
+        if let Some(reply) = reply {
+            self.send(&reply)?;
+        }
+
+        Ok(action)
+    }
+
+    /// Answers `packet`, returning the reply to send, if any.
+    fn handle(
+        &mut self,
+        packet: &str,
+        debugger: &mut Debugger,
+        cpu: &mut CPU,
+        input: &State,
+    ) -> (Option<String>, Action) {
+        let stay = |reply: &str| (Some(reply.to_string()), Action::Stay);
+        let error = || (Some("E01".to_string()), Action::Stay);
+
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        match command {
+            "?" => stay(&stop_reply(debugger, cpu)),
+            "g" => {
+                let registers: Vec<u8> = (0..REGISTERS.len())
+                    .filter_map(|number| read_register(cpu, number))
+                    .flatten()
+                    .collect();
+                stay(&hex_bytes(&registers))
+            }
+            "G" => {
+                let bytes = match parse_hex_bytes(args) {
+                    Some(bytes) => bytes,
+                    None => return error(),
+                };
+                // Checks every register before writing any.
+                let mut values = Vec::new();
+                let mut offset = 0;
+                for (number, (_, bits)) in REGISTERS.iter().enumerate() {
+                    let end = offset + bits / 8;
+                    match bytes.get(offset..end) {
+                        Some(value) if register_value(number, value).is_some() => {
+                            values.push(value)
+                        }
+                        _ => return error(),
+                    }
+                    offset = end;
+                }
+                for (number, value) in values.into_iter().enumerate() {
+                    write_register(cpu, number, value);
+                }
+                // Changed registers or memory can't be replayed.
+                debugger.reset_history();
+                stay("OK")
+            }
+            "p" => match parse_hex(args).and_then(|number| read_register(cpu, number)) {
+                Some(value) => stay(&hex_bytes(&value)),
+                None => error(),
+            },
+            "P" => {
+                let mut parts = args.splitn(2, '=');
+                let number = parts.next().and_then(parse_hex);
+                let value = parts.next().and_then(parse_hex_bytes);
+                match (number, value) {
+                    (Some(number), Some(value)) => match write_register(cpu, number, &value) {
+                        Some(()) => {
+                            debugger.reset_history();
+                            stay("OK")
+                        }
+                        None => error(),
+                    },
+                    _ => error(),
+                }
+            }
+            "m" => match parse_range(args, cpu.memory_size()) {
+                Some((address, length)) => stay(&hex_bytes(&cpu.ram()[address..address + length])),
+                None => error(),
+            },
+            "M" => {
+                let mut parts = args.splitn(2, ':');
+                let range = parts
+                    .next()
+                    .and_then(|range| parse_range(range, cpu.memory_size()));
+                let data = parts.next().and_then(parse_hex_bytes);
+                match (range, data) {
+                    (Some((address, length)), Some(ref data)) if data.len() == length => {
+                        cpu.poke(address, data);
+                        debugger.reset_history();
+                        stay("OK")
+                    }
+                    _ => error(),
+                }
+            }
+            "Z" | "z" => {
+                let fields: Vec<&str> = args.split(',').collect();
+                let address = fields.get(1).and_then(|address| parse_hex(address));
+                let length = fields.get(2).and_then(|length| parse_hex(length));
+                match (fields[0], address) {
+                    ("0", Some(address)) if address < cpu.memory_size() => {
+                        if command == "Z" {
+                            debugger.add_breakpoint(address as u16);
+                        } else {
+                            debugger.remove_breakpoint(address as u16);
+                        }
+                        stay("OK")
+                    }
+                    ("2", Some(address)) | ("3", Some(address)) | ("4", Some(address)) => {
+                        let watchpoint =
+                            length.and_then(|length| watchpoint(fields[0], address, length, cpu));
+                        match watchpoint {
+                            Some(watchpoint) if command == "Z" => {
+                                cpu.watchpoints_mut().add(watchpoint);
+                                stay("OK")
+                            }
+                            Some(watchpoint) => {
+                                cpu.watchpoints_mut().remove_matching(&watchpoint);
+                                stay("OK")
+                            }
+                            None => error(),
+                        }
+                    }
+                    ("0", _) | ("2", _) | ("3", _) | ("4", _) => error(),
+                    // Hardware breakpoints are not supported.
+                    _ => stay(""),
+                }
+            }
+            "s" => {
+                let _ = debugger.tick(cpu, input);
+                debugger.report();
+                stay(&stop_reply(debugger, cpu))
+            }
+            "c" => match debugger.resume(cpu, input) {
+                Ok(()) if debugger.paused() => {
+                    debugger.report();
+                    stay(&stop_reply(debugger, cpu))
+                }
+                Ok(()) => {
+                    self.running = true;
+                    (None, Action::Resume)
+                }
+                Err(fault) => stay(&format!("S{:02x}", signal(Some(fault)))),
+            },
+            "b" if args == "s" => match debugger.reverse_step(cpu, 1) {
+                Ok(()) => stay(&stop_reply(debugger, cpu)),
+                Err(_) => stay(HISTORY_START),
+            },
+            "b" if args == "c" => match debugger.reverse_continue(cpu) {
+                Ok(true) => stay(&stop_reply(debugger, cpu)),
+                _ => stay(HISTORY_START),
+            },
+            "H" => stay("OK"),
+            "k" => (None, Action::Quit),
+            "D" => {
+                self.detach(debugger, cpu, input);
+                (Some("OK".to_string()), Action::Resume)
+            }
+            "q" => stay(&self.query(args)),
+            _ => stay(""),
+        }
+    }
+
+    /// Answers a `q` packet.
+    fn query(&self, query: &str) -> String {
+        const FEATURES: &str = "Xfer:features:read:target.xml:";
+
+        if query.starts_with("Supported") {
+            format!(
+                "PacketSize={:x};qXfer:features:read+;ReverseStep+;ReverseContinue+",
+                PACKET_SIZE
+            )
+        } else if let Some(range) = query.strip_prefix(FEATURES) {
+            let xml = target_xml();
+            match parse_range_unbounded(range) {
+                Some((offset, _)) if offset >= xml.len() => "l".to_string(),
+                Some((offset, length)) if offset + length >= xml.len() => {
+                    format!("l{}", &xml[offset..])
+                }
+                Some((offset, length)) => format!("m{}", &xml[offset..offset + length]),
+                None => "E01".to_string(),
+            }
+        } else if query == "Attached" {
+            "1".to_string()
+        } else if query == "C" {
+            "QC1".to_string()
+        } else if query == "fThreadInfo" {
+            "m1".to_string()
+        } else if query == "sThreadInfo" {
+            "l".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// While the program runs, checks whether GDB asked to interrupt it.
+    pub fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.connection.set_nonblocking(true)?;
+        let result = self.read_byte();
+        self.connection.set_nonblocking(false)?;
+
+        match result {
+            Ok(INTERRUPT) => Ok(true),
+            Ok(_) => Ok(false),
+            Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Tells GDB the program stopped, if it was waiting for it.
+    pub fn report_stop(&mut self, debugger: &Debugger, cpu: &CPU) -> io::Result<()> {
+        if !self.running {
+            return Ok(());
+        }
+        self.running = false;
+
+        self.send(&stop_reply(debugger, cpu))
+    }
+
+    /// Tells GDB the program exited with `EXIT`.
+    pub fn report_exit(&mut self) -> io::Result<()> {
+        self.running = false;
+        self.send("W00")
+    }
+}
+
+/// `offset,length` of `qXfer` packets, which may go past the document.
+fn parse_range_unbounded(range: &str) -> Option<(usize, usize)> {
+    let mut parts = range.splitn(2, ',');
+
+    Some((parse_hex(parts.next()?)?, parse_hex(parts.next()?)?))
+}
+
+#[cfg(test)]
+mod gdb_test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Connection replaying `input` and recording what the stub writes.
+    struct Mock {
+        input: io::Cursor<Vec<u8>>,
+        output: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl Read for Mock {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Mock {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Connection for Mock {
+        fn set_nonblocking(&self, _: bool) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn packet(data: &str) -> String {
+        format!("${}#{:02x}", data, checksum(data.as_bytes()))
+    }
+
+    /// Serves `packets` in order, returning everything written back.
+    fn exchange(packets: &[&str], cpu: &mut CPU, debugger: &mut Debugger) -> String {
+        let input: String = packets.iter().map(|data| packet(data)).collect();
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mock = Mock {
+            input: io::Cursor::new(input.into_bytes()),
+            output: output.clone(),
+        };
+        let mut stub = GdbStub::new(Box::new(mock));
+
+        for _ in packets {
+            stub.serve(debugger, cpu, &State::new()).unwrap();
+        }
+
+        let written = output.borrow().clone();
+        String::from_utf8(written).unwrap()
+    }
+
+    fn setup() -> (CPU, Debugger) {
+        // LD V0, 0x12; LD V1, 0x34; JP 0x204
+        let cpu = CPU::init(&[0x60, 0x12, 0x61, 0x34, 0x12, 0x04]);
+
+        (cpu, Debugger::new(8))
+    }
+
+    #[test]
+    fn test_registers() {
+        let (mut cpu, mut debugger) = setup();
+        cpu.set_i(0x0abc);
+
+        let output = exchange(&["g", "p11", "P10=0123", "p10"], &mut cpu, &mut debugger);
+        let expected = format!(
+            "+{}+{}+{}+{}",
+            packet(&format!("{}0abc0200000000", "00".repeat(16))),
+            packet("0200"),
+            packet("OK"),
+            packet("0123")
+        );
+        assert_eq!(output, expected);
+        assert_eq!(cpu.i(), 0x123);
+    }
+
+    #[test]
+    fn test_write_all_registers() {
+        let (mut cpu, mut debugger) = setup();
+        let registers = format!("{}0123030000", "07".repeat(16));
+
+        let output = exchange(
+            &[&format!("G{}0506", registers), "s"],
+            &mut cpu,
+            &mut debugger,
+        );
+        assert_eq!(output, format!("+{}+{}", packet("OK"), packet("S05")));
+        assert_eq!(cpu.v()[0xf], 7);
+        assert_eq!(cpu.st(), 6);
+
+        // An invalid SP leaves every register, and the history, untouched.
+        let registers = format!("{}0456ff0000", "08".repeat(16));
+        let output = exchange(&[&format!("G{}", registers), "bs"], &mut cpu, &mut debugger);
+        assert_eq!(output, format!("+{}+{}", packet("E01"), packet("S05")));
+        assert_eq!(cpu.v()[0], 7);
+        assert_eq!(cpu.i(), 0x123);
+        assert_eq!(cpu.pc(), 0x300);
+    }
+
+    #[test]
+    fn test_memory() {
+        let (mut cpu, mut debugger) = setup();
+
+        let output = exchange(
+            &["m200,4", "M300,2:beef", "mffff,2"],
+            &mut cpu,
+            &mut debugger,
+        );
+        assert_eq!(
+            output,
+            format!("+{}+{}+{}", packet("60126134"), packet("OK"), packet("E01"))
+        );
+        assert_eq!(&cpu.ram()[0x300..0x302], &[0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_step_and_breakpoints() {
+        let (mut cpu, mut debugger) = setup();
+
+        let output = exchange(&["s", "Z0,204,2", "Z1,300,2"], &mut cpu, &mut debugger);
+        assert_eq!(
+            output,
+            format!("+{}+{}+{}", packet("S05"), packet("OK"), packet(""))
+        );
+        assert_eq!(cpu.v()[0], 0x12);
+        assert!(debugger.breakpoints().contains_key(&0x204));
+
+        // 4 KiB of memory until the profile says otherwise.
+        let output = exchange(&["Z0,1000,2", "Z2,fff,2"], &mut cpu, &mut debugger);
+        assert_eq!(output, format!("+{0}+{0}", packet("E01")));
+        assert!(!debugger.breakpoints().contains_key(&0x1000));
+        assert!(cpu.watchpoints().is_empty());
+    }
+
+    #[test]
+    fn test_continue() {
+        let (mut cpu, mut debugger) = setup();
+        debugger.add_breakpoint(0x204);
+
+        let output = exchange(&["c"], &mut cpu, &mut debugger);
+        assert_eq!(output, "+");
+        assert!(!debugger.paused());
+
+        debugger.run_frame(&mut cpu, &State::new()).unwrap();
+        assert!(debugger.paused());
+        assert_eq!(cpu.pc(), 0x204);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        // LD I, 0x300; LD V0, 0x07; LD [I], V0; JP 0x206
+        let mut cpu = CPU::init(&[0xA3, 0x00, 0x60, 0x07, 0xF0, 0x55, 0x12, 0x06]);
+        let mut debugger = Debugger::new(8);
+
+        let output = exchange(&["Z2,300,1", "c"], &mut cpu, &mut debugger);
+        assert_eq!(output, format!("+{}+", packet("OK")));
+
+        debugger.run_frame(&mut cpu, &State::new()).unwrap();
+        assert!(debugger.paused());
+        assert_eq!(cpu.pc(), 0x206);
+        assert_eq!(stop_reply(&debugger, &cpu), "T05watch:300;");
+
+        exchange(&["z2,300,1"], &mut cpu, &mut debugger);
+        assert!(cpu.watchpoints().is_empty());
+    }
+
+    #[test]
+    fn test_reverse() {
+        let (mut cpu, mut debugger) = setup();
+
+        let output = exchange(&["bs", "s", "s", "bs", "bc"], &mut cpu, &mut debugger);
+        assert_eq!(
+            output,
+            format!("+{0}+{1}+{1}+{1}+{0}", packet(HISTORY_START), packet("S05"))
+        );
+        assert_eq!(cpu.pc(), 0x200);
+        assert_eq!(cpu.v()[0], 0);
+    }
+
+    #[test]
+    fn test_detach() {
+        let (mut cpu, mut debugger) = setup();
+        debugger.add_breakpoint(0x204);
+
+        let output = exchange(&["D"], &mut cpu, &mut debugger);
+        assert_eq!(output, format!("+{}", packet("OK")));
+        assert!(debugger.breakpoints().is_empty());
+        assert!(!debugger.paused());
+    }
+
+    #[test]
+    fn test_bad_checksum() {
+        let (mut cpu, mut debugger) = setup();
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mock = Mock {
+            input: io::Cursor::new(format!("$g#00{}", packet("?")).into_bytes()),
+            output: output.clone(),
+        };
+        let mut stub = GdbStub::new(Box::new(mock));
+
+        stub.serve(&mut debugger, &mut cpu, &State::new()).unwrap();
+        let written = String::from_utf8(output.borrow().clone()).unwrap();
+        assert_eq!(written, format!("-+{}", packet("S05")));
+    }
+
+    #[test]
+    fn test_non_ascii_packet() {
+        let (mut cpu, mut debugger) = setup();
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let mock = Mock {
+            input: io::Cursor::new(b"$\xff#ff".to_vec()),
+            output: output.clone(),
+        };
+        let mut stub = GdbStub::new(Box::new(mock));
+
+        stub.serve(&mut debugger, &mut cpu, &State::new()).unwrap();
+        let written = String::from_utf8(output.borrow().clone()).unwrap();
+        assert_eq!(written, format!("+{}", packet("")));
+    }
+
+    #[test]
+    fn test_target_xml() {
+        let (mut cpu, mut debugger) = setup();
+
+        let output = exchange(
+            &[
+                "qSupported:multiprocess+",
+                "qXfer:features:read:target.xml:0,10",
+            ],
+            &mut cpu,
+            &mut debugger,
+        );
+        assert!(output.contains("qXfer:features:read+"));
+        assert!(output.contains(&packet(&format!("m{}", &target_xml()[..0x10]))));
+    }
+}
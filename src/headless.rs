@@ -0,0 +1,114 @@
+use crate::clock::VirtualClock;
+use crate::cpu::CPU;
+use crate::fault::CpuFault;
+use crate::scheduler::Scheduler;
+use crate::script::InputScript;
+
+/// How long a headless run lasts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Frames(u64),
+    /// Instructions, the last frame being cut short if needed.
+    Cycles(u64),
+}
+
+/// Runs `cpu` without a display on a virtual clock, as fast as the host
+/// allows, feeding it the keys of `script`.
+///
+/// Stops early when the program exits. Returns the number of frames run.
+///
+/// Panics on a `Limit::Cycles` with no instructions per frame, which would
+/// never be reached.
+pub fn run(
+    cpu: &mut CPU,
+    instructions_per_frame: usize,
+    limit: Limit,
+    script: &InputScript,
+) -> Result<u64, CpuFault> {
+    if let Limit::Cycles(_) = limit {
+        assert!(
+            instructions_per_frame > 0,
+            "a cycle limit needs instructions to run"
+        );
+    }
+
+    let mut scheduler = Scheduler::with_clock(instructions_per_frame, VirtualClock::new());
+    let mut cycles = 0;
+
+    while !cpu.halted() {
+        let frame = scheduler.frames();
+        match limit {
+            Limit::Frames(frames) if frame >= frames => break,
+            Limit::Cycles(limit) if cycles >= limit => break,
+            Limit::Cycles(limit) => {
+                let remaining = (limit - cycles).min(instructions_per_frame as u64);
+                scheduler.set_instructions_per_frame(remaining as usize);
+                cycles += remaining;
+            }
+            Limit::Frames(_) => {}
+        }
+
+        scheduler.run_frame(cpu, &script.state_at(frame))?;
+        scheduler.wait_frame();
+    }
+
+    Ok(scheduler.frames())
+}
+
+#[cfg(test)]
+mod headless_test {
+    use super::*;
+    use crate::keypad::State;
+
+    fn counter_cpu() -> CPU {
+        // ADD V0, 1; JP 0x200
+        CPU::init(&[0x70, 0x01, 0x12, 0x00])
+    }
+
+    #[test]
+    fn test_run_frames() {
+        let mut cpu = counter_cpu();
+
+        let frames = run(&mut cpu, 10, Limit::Frames(3), &InputScript::new()).unwrap();
+        assert_eq!(frames, 3);
+        assert_eq!(cpu.v()[0], 15);
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        let mut cpu = counter_cpu();
+
+        let frames = run(&mut cpu, 10, Limit::Cycles(25), &InputScript::new()).unwrap();
+        assert_eq!(frames, 3);
+        assert_eq!(cpu.v()[0], 13);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_run_cycles_without_instructions() {
+        let mut cpu = counter_cpu();
+
+        let _ = run(&mut cpu, 0, Limit::Cycles(25), &InputScript::new());
+    }
+
+    #[test]
+    fn test_run_script() {
+        // LD V1, 7; SKNP V1; EXIT; JP 0x202
+        let mut cpu = CPU::init(&[0x61, 0x07, 0xE1, 0xA1, 0x00, 0xFD, 0x12, 0x02]);
+        let mut script = InputScript::new();
+        script.press(5, State::from_raw(1 << 7));
+
+        let frames = run(&mut cpu, 10, Limit::Frames(60), &script).unwrap();
+        assert_eq!(frames, 6);
+        assert!(cpu.halted());
+    }
+
+    #[test]
+    fn test_run_fault() {
+        // RET
+        let mut cpu = CPU::init(&[0x00, 0xEE]);
+
+        let fault = run(&mut cpu, 10, Limit::Frames(1), &InputScript::new());
+        assert_eq!(fault, Err(CpuFault::StackUnderflow { pc: 0x200 }));
+    }
+}
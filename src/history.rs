@@ -0,0 +1,177 @@
+use std::collections::VecDeque;
+
+use crate::cpu::CPU;
+use crate::keypad::State;
+use crate::snapshot::Snapshot;
+
+/// CPU state before an instruction, with the position of that instruction
+/// in its frame.
+pub struct Checkpoint {
+    pub snapshot: Snapshot,
+    pub frame_position: usize,
+}
+
+impl Checkpoint {
+    pub fn cycles(&self) -> u64 {
+        self.snapshot.cycles
+    }
+}
+
+/// Checkpoints taken every `interval` instructions and the keypad states
+/// in between, from which any recent instruction can be reached again by
+/// re-executing the program.
+///
+/// The program is expected to run forward from one `record()` to the next:
+/// when its cycle count jumps, as after loading a state, the history
+/// restarts.
+pub struct History {
+    interval: u64,
+    capacity: usize,
+    checkpoints: VecDeque<Checkpoint>,
+    // Keypad states from a cycle on, the first one covering the oldest
+    // checkpoint.
+    inputs: VecDeque<(u64, State)>,
+    // Cycle count at the last `record()`.
+    last: Option<u64>,
+}
+
+impl History {
+    /// Keeps up to `capacity` checkpoints, `interval` instructions apart.
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        assert!(interval > 0 && capacity > 0);
+
+        Self {
+            interval,
+            capacity,
+            checkpoints: VecDeque::new(),
+            inputs: VecDeque::new(),
+            last: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.checkpoints.clear();
+        self.inputs.clear();
+        self.last = None;
+    }
+
+    /// Cycle count of the oldest instruction which can be reached.
+    pub fn start(&self) -> Option<u64> {
+        self.checkpoints.front().map(Checkpoint::cycles)
+    }
+
+    /// Records that the next instruction of `cpu` runs with `input`.
+    pub fn record(&mut self, cpu: &CPU, frame_position: usize, input: &State) {
+        let cycles = cpu.cycles();
+        // A faulting or halted CPU doesn't count cycles.
+        match self.last {
+            Some(last) if cycles == last || cycles == last + 1 => {}
+            Some(_) => self.clear(),
+            None => {}
+        }
+        self.last = Some(cycles);
+
+        let due = match self.checkpoints.back() {
+            Some(checkpoint) => cycles >= checkpoint.cycles() + self.interval,
+            None => true,
+        };
+        if due {
+            self.checkpoints.push_back(Checkpoint {
+                snapshot: cpu.snapshot(),
+                frame_position,
+            });
+            if self.checkpoints.len() > self.capacity {
+                self.checkpoints.pop_front();
+            }
+        }
+
+        match self.inputs.back_mut() {
+            Some((_, last)) if last == input => {}
+            Some((from, last)) if *from == cycles => *last = *input,
+            _ => self.inputs.push_back((cycles, *input)),
+        }
+
+        // Keeps the input in effect at the oldest checkpoint.
+        let start = self.start().unwrap_or(cycles);
+        while self.inputs.len() > 1 && self.inputs[1].0 <= start {
+            self.inputs.pop_front();
+        }
+    }
+
+    /// Forgets what followed `cycles`, the program having been taken back
+    /// there and being about to run differently.
+    pub fn truncate(&mut self, cycles: u64) {
+        while self
+            .checkpoints
+            .back()
+            .is_some_and(|checkpoint| checkpoint.cycles() > cycles)
+        {
+            self.checkpoints.pop_back();
+        }
+        while self.inputs.len() > 1 && self.inputs.back().is_some_and(|(from, _)| *from > cycles) {
+            self.inputs.pop_back();
+        }
+        self.last = Some(cycles);
+    }
+
+    /// Latest checkpoint at or before `cycles`.
+    pub fn checkpoint(&self, cycles: u64) -> Option<&Checkpoint> {
+        self.checkpoints
+            .iter()
+            .rev()
+            .find(|checkpoint| checkpoint.cycles() <= cycles)
+    }
+
+    /// Latest checkpoint strictly before `cycles`.
+    pub fn checkpoint_before(&self, cycles: u64) -> Option<&Checkpoint> {
+        cycles
+            .checked_sub(1)
+            .and_then(|cycles| self.checkpoint(cycles))
+    }
+
+    /// Keypad state the instruction at `cycles` ran with.
+    pub fn input(&self, cycles: u64) -> State {
+        self.inputs
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= cycles)
+            .map_or_else(State::new, |(_, input)| *input)
+    }
+}
+
+#[cfg(test)]
+mod history_test {
+    use super::*;
+
+    fn run(history: &mut History, cpu: &mut CPU, instructions: u64) {
+        for _ in 0..instructions {
+            let input = State::from_raw((cpu.cycles() / 5) as u16);
+            history.record(cpu, 0, &input);
+            cpu.tick(&input).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        // ADD V0, 1; JP 0x200
+        let mut cpu = CPU::init(&[0x70, 0x01, 0x12, 0x00]);
+        let mut history = History::new(10, 3);
+
+        run(&mut history, &mut cpu, 45);
+        assert_eq!(history.start(), Some(20));
+        assert_eq!(history.checkpoint(39).unwrap().cycles(), 30);
+        assert_eq!(history.checkpoint_before(40).unwrap().cycles(), 30);
+        assert!(history.checkpoint(19).is_none());
+        assert_eq!(history.input(20), State::from_raw(4));
+        assert_eq!(history.input(44), State::from_raw(8));
+
+        history.truncate(35);
+        assert!(history.checkpoint(44).unwrap().cycles() == 30);
+        assert_eq!(history.input(44), State::from_raw(7));
+
+        cpu.restore(&history.checkpoint(20).unwrap().snapshot);
+        run(&mut history, &mut cpu, 1);
+        assert_eq!(history.start(), Some(20));
+        assert_eq!(history.checkpoint(44).unwrap().cycles(), 20);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::cpu::{CPU, INSTRUCTION_LENGTH};
+use crate::decoder::Instruction;
+
+/// Instructions from `context` instructions before `around` to `context`
+/// after, with their addresses.
+///
+/// Instructions before `around` are assumed to be two bytes long.
+pub fn disassemble(ram: &[u8], around: usize, context: usize) -> Vec<(usize, Instruction)> {
+    let mut address = around.saturating_sub(context * INSTRUCTION_LENGTH as usize);
+    let mut instructions = Vec::new();
+
+    for _ in 0..context * 2 + 1 {
+        if address >= ram.len() {
+            break;
+        }
+        let instruction = Instruction::fetch(ram, address);
+        let length = instruction.length() as usize;
+
+        instructions.push((address, instruction));
+        address += length;
+    }
+
+    instructions
+}
+
+/// Lines showing V0 to VF on two columns, then I, PC, SP, DT and ST.
+pub fn registers(cpu: &CPU) -> Vec<String> {
+    let v = cpu.v();
+    let mut lines: Vec<String> = (0..8)
+        .map(|x| format!("V{:X} {:02x}  V{:X} {:02x}", x, v[x], x + 8, v[x + 8]))
+        .collect();
+
+    lines.push(format!("I  {:04x}", cpu.i()));
+    lines.push(format!("PC {:04x}", cpu.pc()));
+    lines.push(format!("SP {:02x}", cpu.sp()));
+    lines.push(format!("DT {:02x}", cpu.dt()));
+    lines.push(format!("ST {:02x}", cpu.st()));
+
+    lines
+}
+
+/// One line per stack entry, the entries in use marked with `>`.
+pub fn stack(cpu: &CPU) -> Vec<String> {
+    cpu.stack()
+        .iter()
+        .enumerate()
+        .map(|(index, address)| {
+            let marker = if index < cpu.sp() { '>' } else { ' ' };
+            format!("{}{:X} {:04x}", marker, index, address)
+        })
+        .collect()
+}
+
+/// Frames since each byte of memory last changed.
+///
+/// Changes are found by comparing the memory from one `update()` to the
+/// next, so writes of the value already there don't count.
+pub struct MemoryActivity {
+    previous: Vec<u8>,
+    // Update at which each byte last changed.
+    changed: Vec<Option<u64>>,
+    updates: u64,
+}
+
+impl MemoryActivity {
+    pub fn new(ram: &[u8]) -> Self {
+        Self {
+            previous: ram.to_vec(),
+            changed: vec![None; ram.len()],
+            updates: 0,
+        }
+    }
+
+    pub fn update(&mut self, ram: &[u8]) {
+        self.updates += 1;
+
+        for (address, (previous, &byte)) in self.previous.iter_mut().zip(ram).enumerate() {
+            if *previous != byte {
+                *previous = byte;
+                self.changed[address] = Some(self.updates);
+            }
+        }
+    }
+
+    /// Updates since the byte at `address` changed, if it ever did.
+    pub fn age(&self, address: usize) -> Option<u64> {
+        self.changed
+            .get(address)
+            .copied()
+            .flatten()
+            .map(|changed| self.updates - changed)
+    }
+}
+
+#[cfg(test)]
+mod inspector_test {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        // CLS; LD I, 0x300; JP 0x200
+        let cpu = CPU::init(&[0x00, 0xE0, 0xA3, 0x00, 0x12, 0x00]);
+
+        let instructions = disassemble(cpu.ram(), 0x202, 1);
+        let addresses: Vec<usize> = instructions.iter().map(|(address, _)| *address).collect();
+        assert_eq!(addresses, vec![0x200, 0x202, 0x204]);
+        assert_eq!(instructions[1].1.to_string(), "LD I, 0x300");
+
+        assert_eq!(disassemble(cpu.ram(), 0xffe, 2).len(), 3);
+    }
+
+    #[test]
+    fn test_registers_and_stack() {
+        let mut cpu = CPU::new();
+        cpu.set_v(0x9, 0xab);
+        cpu.set_i(0x123);
+        cpu.set_sp(1);
+
+        let lines = registers(&cpu);
+        assert_eq!(lines[1], "V1 00  V9 ab");
+        assert_eq!(lines[8], "I  0123");
+        assert_eq!(lines.len(), 13);
+
+        let lines = stack(&cpu);
+        assert_eq!(lines.len(), 16);
+        assert_eq!(lines[0], ">0 0000");
+        assert_eq!(lines[1], " 1 0000");
+    }
+
+    #[test]
+    fn test_memory_activity() {
+        let mut ram = vec![0; 4];
+        let mut activity = MemoryActivity::new(&ram);
+
+        ram[1] = 1;
+        activity.update(&ram);
+        ram[2] = 2;
+        activity.update(&ram);
+        ram[1] = 1;
+        activity.update(&ram);
+
+        assert_eq!(activity.age(0), None);
+        assert_eq!(activity.age(1), Some(2));
+        assert_eq!(activity.age(2), Some(1));
+        assert_eq!(activity.age(4), None);
+    }
+}
@@ -1,65 +1,125 @@
 extern crate sdl2;
 
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Scancode};
 
 use crate::context::Context;
-
-pub struct Keyboard {
-    event_pump: sdl2::EventPump,
+use crust::keymap::Keymap;
+use crust::keypad::State;
+
+/// Rows of the debug window memory view scrolled by Page Up and Down.
+const SCROLL_PAGE: i32 = 16;
+
+/// Keys used by the emulator itself, which can't be bound to the keypad.
+const RESERVED_KEYS: [Scancode; 16] = [
+    Scancode::Escape,
+    Scancode::Tab,
+    Scancode::Backspace,
+    Scancode::PageUp,
+    Scancode::PageDown,
+    Scancode::Home,
+    Scancode::F1,
+    Scancode::F2,
+    Scancode::F3,
+    Scancode::F4,
+    Scancode::F5,
+    Scancode::F6,
+    Scancode::F7,
+    Scancode::F8,
+    Scancode::F9,
+    Scancode::F12,
+];
+
+/// Emulator commands bound to keys outside the keypad.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hotkey {
+    SaveState,
+    LoadState,
+    NextSlot,
+    /// Pause into the debugger.
+    Break,
+    Pause,
+    /// Show or hide the keypad over the screen.
+    Hints,
+    /// Run one frame while paused.
+    FrameAdvance,
+    /// Cycle through full, half and quarter speed.
+    SlowMotion,
+    /// Restart the program, keeping memory.
+    SoftReset,
+    /// Reload the ROM into a new CPU.
+    HardReset,
 }
 
-#[repr(u16)]
-pub enum KeyPad {
-    Key0 = 1 << 0x00,
-    Key1 = 1 << 0x01,
-    Key2 = 1 << 0x02,
-    Key3 = 1 << 0x03,
-    Key4 = 1 << 0x04,
-    Key5 = 1 << 0x05,
-    Key6 = 1 << 0x06,
-    Key7 = 1 << 0x07,
-    Key8 = 1 << 0x08,
-    Key9 = 1 << 0x09,
-    KeyA = 1 << 0x0A,
-    KeyB = 1 << 0x0B,
-    KeyC = 1 << 0x0C,
-    KeyD = 1 << 0x0D,
-    KeyE = 1 << 0x0E,
-    KeyF = 1 << 0x0F,
+/// Move of the memory view of the debug window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scroll {
+    Rows(i32),
+    /// Back to following I.
+    Index,
 }
 
-pub struct State {
-    state: u16,
+/// Keys read by one `Keyboard::poll()`.
+pub struct Input {
+    pub keypad: State,
+    /// Hotkeys pressed since the previous poll, in order.
+    pub hotkeys: Vec<Hotkey>,
+    /// Whether the rewind key is held.
+    pub rewinding: bool,
+    /// Whether the fast-forward key is held.
+    pub fast_forwarding: bool,
+    /// Wheel and Page Up/Down moves since the previous poll, Home going
+    /// back to following I.
+    pub scroll: Option<Scroll>,
 }
 
-impl State {
-    pub fn new() -> Self {
-        Self { state: 0 }
-    }
-
-    pub fn add_key(&mut self, key: KeyPad) {
-        self.state |= key as u16
+fn scroll_rows(scroll: &mut Option<Scroll>, rows: i32) {
+    *scroll = match *scroll {
+        Some(Scroll::Rows(previous)) => Some(Scroll::Rows(previous + rows)),
+        _ => Some(Scroll::Rows(rows)),
     }
+}
 
-    pub fn has_key(&self, keypos: u8) -> bool {
-        (self.state & (1 << keypos)) != 0
+/// Label of the key at the position of `scancode` on the host's layout.
+fn label(scancode: Scancode) -> String {
+    match Keycode::from_scancode(scancode).map(Keycode::name) {
+        Some(name) if !name.is_empty() => name,
+        _ => scancode.name().to_string(),
     }
+}
 
-    pub fn as_raw(&self) -> u16 {
-        self.state
-    }
+pub struct Keyboard {
+    event_pump: sdl2::EventPump,
+    bindings: Vec<(Scancode, u8)>,
 }
 
 impl Keyboard {
-    pub fn new(context: &Context) -> Self {
-        Self {
+    /// Reads the keypad through `keymap`, whose names are those of the
+    /// physical keys on a US QWERTY keyboard. Fails on unknown key names and
+    /// on keys used by the emulator.
+    pub fn new(context: &Context, keymap: &Keymap) -> Result<Self, String> {
+        let bindings = keymap.resolve(Scancode::from_name, &RESERVED_KEYS)?;
+
+        Ok(Self {
             event_pump: context.as_raw().event_pump().unwrap(),
+            bindings,
+        })
+    }
+
+    /// Labels of the host keys bound to each CHIP-8 key, on the host's
+    /// keyboard layout.
+    pub fn labels(&self) -> Vec<Vec<String>> {
+        let mut labels = vec![Vec::new(); 16];
+
+        for &(scancode, key) in self.bindings.iter() {
+            labels[key as usize].push(label(scancode));
         }
+        labels
     }
 
-    pub fn poll(&mut self) -> Result<State, ()> {
-        let mut state = State::new();
+    pub fn poll(&mut self) -> Result<Input, ()> {
+        let mut hotkeys = Vec::new();
+        let mut scroll = None;
 
         for event in self.event_pump.poll_iter() {
             match event {
@@ -68,57 +128,54 @@ impl Keyboard {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => return Err(()),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => match keycode {
+                    Keycode::F1 => hotkeys.push(Hotkey::Hints),
+                    Keycode::F2 => hotkeys.push(Hotkey::Pause),
+                    Keycode::F3 => hotkeys.push(Hotkey::FrameAdvance),
+                    Keycode::F4 => hotkeys.push(Hotkey::SlowMotion),
+                    Keycode::F5 => hotkeys.push(Hotkey::SaveState),
+                    Keycode::F6 => hotkeys.push(Hotkey::NextSlot),
+                    Keycode::F7 => hotkeys.push(Hotkey::LoadState),
+                    Keycode::F8 => hotkeys.push(Hotkey::SoftReset),
+                    Keycode::F9 => hotkeys.push(Hotkey::HardReset),
+                    Keycode::F12 => hotkeys.push(Hotkey::Break),
+                    Keycode::PageUp => scroll_rows(&mut scroll, -SCROLL_PAGE),
+                    Keycode::PageDown => scroll_rows(&mut scroll, SCROLL_PAGE),
+                    Keycode::Home => scroll = Some(Scroll::Index),
+                    _ => (),
+                },
+                Event::MouseWheel { y, .. } => scroll_rows(&mut scroll, -y),
                 _ => (),
             }
         }
 
-        let keys: Vec<Keycode> = self
+        // The keypad follows the physical position of the keys, whatever
+        // their labels on the host's layout.
+        let keys: Vec<Scancode> = self
             .event_pump
             .keyboard_state()
             .pressed_scancodes()
-            .filter_map(Keycode::from_scancode)
             .collect();
 
-        for key in keys {
-            match key {
-                Keycode::Num1 => state.add_key(KeyPad::Key1),
-                Keycode::Num2 => state.add_key(KeyPad::Key2),
-                Keycode::Num3 => state.add_key(KeyPad::Key3),
-                Keycode::Num4 => state.add_key(KeyPad::KeyC),
-                Keycode::Q => state.add_key(KeyPad::Key4),
-                Keycode::W => state.add_key(KeyPad::Key5),
-                Keycode::E => state.add_key(KeyPad::Key6),
-                Keycode::R => state.add_key(KeyPad::KeyD),
-                Keycode::A => state.add_key(KeyPad::Key7),
-                Keycode::S => state.add_key(KeyPad::Key8),
-                Keycode::D => state.add_key(KeyPad::Key9),
-                Keycode::F => state.add_key(KeyPad::KeyE),
-                Keycode::Z => state.add_key(KeyPad::KeyA),
-                Keycode::X => state.add_key(KeyPad::Key0),
-                Keycode::C => state.add_key(KeyPad::KeyB),
-                Keycode::V => state.add_key(KeyPad::KeyF),
-                _ => (),
-            }
-        }
-
-        return Ok(state);
-    }
-}
-
-#[cfg(test)]
-mod keyboard_test {
-    use super::*;
-
-    #[test]
-    fn test_has_key() {
-        let mut state = State::new();
-
-        state.add_key(KeyPad::Key0);
-        assert!(state.has_key(0));
-
-        state.add_key(KeyPad::KeyA);
-        assert!(state.has_key(0xa));
-
-        assert!(!state.has_key(0x9));
+        let rewinding = keys.contains(&Scancode::Backspace);
+        let fast_forwarding = keys.contains(&Scancode::Tab);
+
+        let state = self
+            .bindings
+            .iter()
+            .filter(|(scancode, _)| keys.contains(scancode))
+            .fold(0, |state, (_, key)| state | 1 << key);
+
+        Ok(Input {
+            keypad: State::from_raw(state),
+            hotkeys,
+            rewinding,
+            fast_forwarding,
+            scroll,
+        })
     }
 }
@@ -0,0 +1,276 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::keypad::LAYOUT;
+
+/// The 4x4 block of keys at the left of the keyboard, matching the keypad,
+/// and the arrow keys on the common 2/4/6/8 movement layout.
+const DEFAULT_KEYS: [&[&str]; 16] = [
+    &["X"],
+    &["1"],
+    &["2", "Up"],
+    &["3"],
+    &["Q", "Left"],
+    &["W"],
+    &["E", "Right"],
+    &["A"],
+    &["S", "Down"],
+    &["D"],
+    &["Z"],
+    &["C"],
+    &["4"],
+    &["R"],
+    &["F"],
+    &["V"],
+];
+
+/// Host key names bound to each of the 16 CHIP-8 keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    keys: Vec<Vec<String>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            keys: DEFAULT_KEYS
+                .iter()
+                .map(|names| names.iter().map(|name| name.to_string()).collect())
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Host keys bound to the CHIP-8 key `key`, 0 to F.
+    pub fn keys(&self, key: u8) -> &[String] {
+        &self.keys[key as usize]
+    }
+
+    pub fn set_keys(&mut self, key: u8, names: Vec<String>) {
+        self.keys[key as usize] = names
+    }
+
+    /// All bindings, as (CHIP-8 key, host key name) pairs.
+    pub fn bindings(&self) -> impl Iterator<Item = (u8, &str)> {
+        self.keys
+            .iter()
+            .enumerate()
+            .flat_map(|(key, names)| names.iter().map(move |name| (key as u8, name.as_str())))
+    }
+
+    /// Host keys of the bindings, as (host key, CHIP-8 key) pairs, `lookup`
+    /// giving the key of a name. Fails on unknown names and on the `reserved`
+    /// keys.
+    pub fn resolve<K: Copy + PartialEq>(
+        &self,
+        lookup: impl Fn(&str) -> Option<K>,
+        reserved: &[K],
+    ) -> Result<Vec<(K, u8)>, String> {
+        self.bindings()
+            .map(|(key, name)| match lookup(name) {
+                Some(host) if reserved.contains(&host) => Err(format!(
+                    "Key '{}' bound to {:X} is used by the emulator",
+                    name, key
+                )),
+                Some(host) => Ok((host, key)),
+                None => Err(format!("Unknown key '{}' bound to {:X}", name, key)),
+            })
+            .collect()
+    }
+
+    /// Applies the bindings of a keymap file, the other keys keeping theirs.
+    ///
+    /// Each line binds a hex digit to comma-separated physical key names, as
+    /// labeled on a US QWERTY keyboard; `#` starts a comment:
+    ///
+    /// ```text
+    /// 2 = 2, Up
+    /// ```
+    pub fn apply(&mut self, text: &str) -> Result<(), String> {
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = || format!("line {}: expected '<key> = <host keys>'", number + 1);
+            let mut fields = line.splitn(2, '=');
+            let key = fields.next().ok_or_else(error)?.trim();
+            let names = fields.next().ok_or_else(error)?;
+
+            let key = match u8::from_str_radix(key, 16) {
+                Ok(key) if key < 16 => key,
+                _ => return Err(format!("line {}: '{}' is not a key", number + 1, key)),
+            };
+            let names = names
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect();
+
+            self.set_keys(key, names);
+        }
+
+        Ok(())
+    }
+
+    /// Applies the keymap file at `path`.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let context = |error: String| format!("{}: {}", path.display(), error);
+        let text = fs::read_to_string(path).map_err(|error| context(error.to_string()))?;
+
+        self.apply(&text).map_err(context)
+    }
+}
+
+/// The keypad next to the first host key bound to each of its keys, then the
+/// other bindings, `labels` giving the host keys of each CHIP-8 key.
+pub fn format_keyboard(labels: &[Vec<String>]) -> String {
+    let first = |key: u8| labels[key as usize].first().map_or("-", String::as_str);
+    let mut text = String::new();
+
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        let keypad: String = keys.iter().map(|key| format!("[{:X}]", key)).collect();
+        let host: String = keys
+            .iter()
+            .map(|&key| format!("[{}]", first(key)))
+            .collect();
+        let separator = if row == 1 || row == 2 {
+            "  ==  "
+        } else {
+            "      "
+        };
+        text += &format!("{}{}{}\n", keypad, separator, host);
+    }
+
+    let others: Vec<String> = LAYOUT
+        .iter()
+        .flatten()
+        .flat_map(|&key| {
+            labels[key as usize]
+                .iter()
+                .skip(1)
+                .map(move |label| format!("{:X} = {}", key, label))
+        })
+        .collect();
+    if !others.is_empty() {
+        text += &format!("Also {}\n", others.join(", "));
+    }
+
+    text
+}
+
+/// Default keymap of the user, `$XDG_CONFIG_HOME/crust/keymap` or
+/// `~/.config/crust/keymap`.
+pub fn user_keymap_path() -> Option<PathBuf> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config) if !config.is_empty() => PathBuf::from(config),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config.join("crust").join("keymap"))
+}
+
+/// Keymap overriding the default one for the ROM at `rom_path`, as
+/// `rom.ch8.keymap`.
+pub fn rom_keymap_path(rom_path: &Path) -> PathBuf {
+    let mut path = rom_path.as_os_str().to_owned();
+    path.push(".keymap");
+
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod keymap_test {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let keymap = Keymap::new();
+
+        assert_eq!(keymap.keys(0x0), ["X"]);
+        assert_eq!(keymap.keys(0x8), ["S", "Down"]);
+        assert_eq!(keymap.bindings().count(), 20);
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut keymap = Keymap::new();
+        keymap
+            .apply("# arrows only\n5 = Space, Return  # fire\nf =\n\nA = Left Shift\n")
+            .unwrap();
+
+        assert_eq!(keymap.keys(0x5), ["Space", "Return"]);
+        assert!(keymap.keys(0xF).is_empty());
+        assert_eq!(keymap.keys(0xA), ["Left Shift"]);
+        assert_eq!(keymap.keys(0x1), ["1"]);
+
+        assert!(keymap.apply("10 = X").is_err());
+        assert!(keymap.apply("5 X").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let names = ["X", "1", "2", "Up", "Escape"];
+        let lookup = |name: &str| names.iter().position(|&known| known == name);
+        let mut keymap = Keymap::new();
+        keymap.apply("0 = X\n1 = 1\n2 = 2, Up").unwrap();
+        for key in 3..16 {
+            keymap.set_keys(key, Vec::new());
+        }
+
+        assert_eq!(
+            keymap.resolve(lookup, &[4]),
+            Ok(vec![(0, 0x0), (1, 0x1), (2, 0x2), (3, 0x2)])
+        );
+
+        keymap.set_keys(0x5, vec!["Escape".to_string()]);
+        assert_eq!(
+            keymap.resolve(lookup, &[4]),
+            Err("Key 'Escape' bound to 5 is used by the emulator".to_string())
+        );
+
+        keymap.set_keys(0x5, vec!["Nope".to_string()]);
+        assert_eq!(
+            keymap.resolve(lookup, &[4]),
+            Err("Unknown key 'Nope' bound to 5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_keyboard() {
+        // The default keymap as labeled on an AZERTY layout.
+        let mut labels: Vec<Vec<String>> = [
+            "X", "&", "é", "\"", "A", "Z", "E", "Q", "S", "D", "W", "C", "'", "R", "F", "V",
+        ]
+        .iter()
+        .map(|label| vec![label.to_string()])
+        .collect();
+        labels[0x2].push("Up".to_string());
+        labels[0xF].clear();
+
+        assert_eq!(
+            format_keyboard(&labels),
+            "[1][2][3][C]      [&][é][\"][']\n\
+             [4][5][6][D]  ==  [A][Z][E][R]\n\
+             [7][8][9][E]  ==  [Q][S][D][F]\n\
+             [A][0][B][F]      [W][X][C][-]\n\
+             Also 2 = Up\n"
+        );
+    }
+
+    #[test]
+    fn test_rom_keymap_path() {
+        assert_eq!(
+            rom_keymap_path(Path::new("roms/pong.ch8")),
+            PathBuf::from("roms/pong.ch8.keymap")
+        );
+    }
+}
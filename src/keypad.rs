@@ -0,0 +1,85 @@
+/// Keys of the hex keypad, row by row, as laid out on the COSMAC VIP.
+pub const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+#[repr(u16)]
+pub enum KeyPad {
+    Key0 = 1 << 0x00,
+    Key1 = 1 << 0x01,
+    Key2 = 1 << 0x02,
+    Key3 = 1 << 0x03,
+    Key4 = 1 << 0x04,
+    Key5 = 1 << 0x05,
+    Key6 = 1 << 0x06,
+    Key7 = 1 << 0x07,
+    Key8 = 1 << 0x08,
+    Key9 = 1 << 0x09,
+    KeyA = 1 << 0x0A,
+    KeyB = 1 << 0x0B,
+    KeyC = 1 << 0x0C,
+    KeyD = 1 << 0x0D,
+    KeyE = 1 << 0x0E,
+    KeyF = 1 << 0x0F,
+}
+
+/// Pressed keys of the hex keypad, one bit per key.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct State {
+    state: u16,
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self { state: 0 }
+    }
+
+    pub fn from_raw(state: u16) -> Self {
+        Self { state }
+    }
+
+    pub fn add_key(&mut self, key: KeyPad) {
+        self.state |= key as u16
+    }
+
+    pub fn has_key(&self, keypos: u8) -> bool {
+        keypos < 0x10 && (self.state & (1 << keypos)) != 0
+    }
+
+    pub fn as_raw(&self) -> u16 {
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod keypad_test {
+    use super::*;
+
+    #[test]
+    fn test_has_key() {
+        let mut state = State::new();
+
+        state.add_key(KeyPad::Key0);
+        assert!(state.has_key(0));
+
+        state.add_key(KeyPad::KeyA);
+        assert!(state.has_key(0xa));
+
+        assert!(!state.has_key(0x9));
+        assert!(!state.has_key(0x10));
+        assert!(!state.has_key(0xff));
+    }
+
+    #[test]
+    fn test_from_raw() {
+        let state = State::from_raw(0x8001);
+
+        assert!(state.has_key(0x0));
+        assert!(state.has_key(0xf));
+        assert!(!state.has_key(0x1));
+        assert_eq!(state.as_raw(), 0x8001);
+    }
+}
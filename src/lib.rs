@@ -1,7 +1,41 @@
+pub mod audio;
+pub mod clock;
+pub mod cpu;
+pub mod debugger;
 pub mod decoder;
+pub mod expression;
+pub mod fault;
+pub mod font;
+pub mod gdb;
+pub mod hash;
+pub mod headless;
+pub mod history;
+pub mod inspector;
+pub mod keymap;
+pub mod keypad;
+pub mod quirks;
+pub mod random;
+pub mod rewind;
 pub mod rom;
+pub mod savestate;
+pub mod scheduler;
+pub mod screenshot;
+pub mod script;
+pub mod snapshot;
+pub mod speed;
+pub mod tools;
+pub mod trace;
+pub mod tracediff;
+pub mod vram;
+pub mod watch;
 
-pub const MEMORY_SIZE: usize = 0x1000;
+/// Memory of the XO-CHIP, the largest of the supported machines.
+pub const MEMORY_SIZE: usize = 0x10000;
+/// Memory of the CHIP-8 and SUPER-CHIP interpreters.
+pub const CHIP8_MEMORY_SIZE: usize = 0x1000;
 pub const ROM_SIZE: usize = MEMORY_SIZE - 0x200;
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+pub const FRAME_RATE: u64 = 60;
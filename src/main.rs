@@ -1,53 +1,310 @@
 extern crate clap;
-extern crate hexdump;
 mod buzzer;
 mod context;
-mod cpu;
+mod debug_window;
 mod display;
-mod font;
 mod keyboard;
 mod renderer;
-mod tools;
+mod text;
 
-use std::{thread, time};
+use std::io;
+use std::io::Write;
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::path::Path;
 
 use clap::{App, Arg, ArgMatches};
 
 use buzzer::Buzzer;
 use context::Context;
-use cpu::CPU;
+use debug_window::DebugWindow;
 use display::Display;
 use keyboard::*;
 use renderer::Renderer;
-use tools::*;
 
-use crust::rom::load_rom;
+use crust::cpu::CPU;
+use crust::debugger::{Action, Debugger};
+use crust::decoder::CLASSES;
+use crust::fault::{FaultPolicy, ACTIONS};
+use crust::gdb::{Connection, GdbStub};
+use crust::inspector;
+use crust::keymap::{self, rom_keymap_path, user_keymap_path, Keymap};
+use crust::keypad::State;
+use crust::quirks::{Profile, PROFILES};
+use crust::rewind::{Rewind, DEFAULT_KEYFRAME_INTERVAL};
+use crust::rom::{self, load_rom, RomBuffer};
+use crust::savestate::{slot_path, SaveState, SLOTS};
+use crust::scheduler::Scheduler;
+use crust::speed::{Speed, DEFAULT_FAST_FORWARD};
+use crust::trace::{Trace, TraceOptions};
+use crust::FRAME_RATE;
 
 const UPSCALE: usize = 10;
-const CPU_FREQUENCY: &'static str = "500";
-const IO_FREQUENCY: &'static str = "60";
-
-fn print_keyboard() {
-    print!(
-        "[1][2][3][C]\n\
-         [4][5][6][D]\n\
-         [7][8][9][E]\n\
-         [A][0][B][F]\n",
-    )
+const INSTRUCTIONS_PER_FRAME: &str = "8";
+const QUIRKS: &str = "crust";
+const REWIND_SECONDS: &str = "30";
+
+/// Label of the first host key bound to each CHIP-8 key for the keypad
+/// hints, its keymap name when the label of the host's layout can't be drawn.
+fn hints(keymap: &Keymap, labels: &[Vec<String>]) -> Vec<String> {
+    labels
+        .iter()
+        .enumerate()
+        .map(|(key, labels)| match labels.first() {
+            Some(label) if text::is_drawable(label) => label.clone(),
+            _ => keymap.keys(key as u8).first().cloned().unwrap_or_default(),
+        })
+        .collect()
 }
 
-fn run(matches: &ArgMatches) -> Result<(), String> {
-    let cpu_freq = matches.value_of("cpu_freq").unwrap_or(CPU_FREQUENCY);
-    let io_freq = matches.value_of("io_freq").unwrap_or(IO_FREQUENCY);
+fn parse_seed(seed: &str) -> Result<u64, String> {
+    seed.parse::<u64>()
+        .map_err(|_| format!("Couldn't parse value '{}' as a seed.", seed))
+}
+
+fn parse_instructions_per_frame(matches: &ArgMatches) -> Result<usize, String> {
+    if let Some(frequency) = matches.value_of("cpu_freq") {
+        return match frequency.parse::<u64>() {
+            Ok(value) if value > 0 => Ok(((value + FRAME_RATE / 2) / FRAME_RATE).max(1) as usize),
+            _ => Err(format!(
+                "Couldn't parse value '{}' as a frequency.",
+                frequency
+            )),
+        };
+    }
+
+    let instructions = matches.value_of("ipf").unwrap_or(INSTRUCTIONS_PER_FRAME);
+
+    match instructions.parse::<usize>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(format!(
+            "Couldn't parse value '{}' as a number of instructions.",
+            instructions
+        )),
+    }
+}
+
+fn parse_trace(matches: &ArgMatches) -> Result<Option<Trace>, String> {
+    TraceOptions {
+        path: matches.value_of("trace"),
+        pc_range: matches.value_of("trace_pc"),
+        classes: matches
+            .values_of("trace_class")
+            .map_or_else(Vec::new, Iterator::collect),
+        memory_hash: matches.is_present("trace_memory"),
+    }
+    .create()
+}
+
+fn parse_rewind(matches: &ArgMatches) -> Result<Rewind, String> {
+    let seconds = matches.value_of("rewind").unwrap_or(REWIND_SECONDS);
+    let seconds = seconds
+        .parse::<usize>()
+        .map_err(|_| format!("Couldn't parse value '{}' as a duration.", seconds))?;
+
+    Ok(Rewind::new(
+        seconds * FRAME_RATE as usize,
+        DEFAULT_KEYFRAME_INTERVAL,
+    ))
+}
+
+fn parse_fast_forward(matches: &ArgMatches) -> Result<usize, String> {
+    let frames = match matches.value_of("fast_forward") {
+        Some(frames) => frames,
+        None => return Ok(DEFAULT_FAST_FORWARD),
+    };
+
+    match frames.parse::<usize>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(format!(
+            "Couldn't parse value '{}' as a speed multiplier.",
+            frames
+        )),
+    }
+}
+
+/// Reads the keymap given with `--keymap`, or the user's one, then the
+/// one of the ROM.
+fn load_keymap(matches: &ArgMatches, rom_path: &Path) -> Result<Keymap, String> {
+    let mut keymap = Keymap::new();
+
+    match matches.value_of("keymap") {
+        Some(path) => keymap.load(Path::new(path))?,
+        None => {
+            if let Some(path) = user_keymap_path().filter(|path| path.exists()) {
+                keymap.load(&path)?
+            }
+        }
+    }
+
+    let path = rom_keymap_path(rom_path);
+    if path.exists() {
+        keymap.load(&path)?
+    }
+
+    Ok(keymap)
+}
+
+/// ROM being run, and the settings of the CPUs running it.
+struct Game<'a> {
+    path: &'a Path,
+    rom: RomBuffer,
+    hash: u64,
+    profile: Profile,
+    fault_policy: FaultPolicy,
+    seed: Option<u64>,
+}
+
+impl Game<'_> {
+    /// Loads the ROM into a new CPU.
+    fn boot(&self) -> Result<CPU, String> {
+        let mut cpu = CPU::init(&self.rom);
+        cpu.set_profile(&self.profile)?;
+        cpu.set_fault_policy(self.fault_policy);
+        if let Some(seed) = self.seed {
+            cpu.set_seed(seed);
+        }
+
+        Ok(cpu)
+    }
+}
+
+/// Waits for GDB to connect on a TCP `host:port` or a `unix:<path>` socket.
+fn accept_gdb(address: &str) -> Result<GdbStub, String> {
+    let error = |error: io::Error| format!("{}: {}", address, error);
+    println!("Waiting for GDB on {}", address);
+
+    let connection: Box<dyn Connection> = match address.strip_prefix("unix:") {
+        #[cfg(unix)]
+        Some(path) => {
+            let listener = UnixListener::bind(path).map_err(error)?;
+            Box::new(listener.accept().map_err(error)?.0)
+        }
+        #[cfg(not(unix))]
+        Some(_) => return Err("Unix sockets are not supported on this platform".to_string()),
+        None => {
+            let listener = TcpListener::bind(address).map_err(error)?;
+            let (stream, _) = listener.accept().map_err(error)?;
+            stream.set_nodelay(true).map_err(error)?;
+            Box::new(stream)
+        }
+    };
+
+    Ok(GdbStub::new(connection))
+}
+
+/// Reads and runs one debugger command from stdin, quitting on end of file.
+fn prompt(debugger: &mut Debugger, cpu: &mut CPU, input: &State) -> Action {
+    print!("(crust) ");
+    io::stdout().flush().unwrap();
+
+    let mut command = String::new();
+    match io::stdin().read_line(&mut command) {
+        Ok(0) | Err(_) => return Action::Quit,
+        Ok(_) => {}
+    }
+
+    match debugger.execute(&command, cpu, input) {
+        Ok(response) => {
+            print!("{}", response.output);
+            response.action
+        }
+        Err(error) => {
+            println!("Error: {}", error);
+            Action::Stay
+        }
+    }
+}
+
+fn handle_hotkey(
+    hotkey: Hotkey,
+    cpu: &mut CPU,
+    debugger: Option<&mut Debugger>,
+    speed: &mut Speed,
+    game: &Game,
+    slot: &mut u8,
+    renderer: &mut Renderer,
+) {
+    let path = slot_path(game.path, *slot);
+
+    match hotkey {
+        Hotkey::SaveState => match SaveState::new(game.hash, cpu.snapshot()).save(&path) {
+            Ok(()) => println!("Saved state to slot {}", slot),
+            Err(error) => println!("Couldn't save state to slot {}: {}", slot, error),
+        },
+        Hotkey::LoadState => match SaveState::load(&path, game.hash) {
+            Ok(state) => {
+                cpu.restore(state.snapshot());
+                if let Some(debugger) = debugger {
+                    debugger.reset_history();
+                }
+                println!("Loaded state from slot {}", slot)
+            }
+            Err(error) => println!("Couldn't load state from slot {}: {}", slot, error),
+        },
+        Hotkey::NextSlot => {
+            *slot = (*slot + 1) % SLOTS;
+            println!("Selected save state slot {}", slot)
+        }
+        Hotkey::Break => match debugger {
+            Some(debugger) => {
+                debugger.pause();
+                print!("Paused at {}", debugger.current(cpu))
+            }
+            None => println!("Run with --debug to break into the debugger"),
+        },
+        Hotkey::Pause => {
+            speed.toggle_pause();
+            if speed.paused() {
+                println!("Paused, F3 advances one frame")
+            } else {
+                println!("Resumed")
+            }
+        }
+        Hotkey::Hints => renderer.toggle_hints(),
+        Hotkey::FrameAdvance => speed.advance(),
+        Hotkey::SlowMotion => match speed.next_slow_motion() {
+            1 => println!("Full speed"),
+            divisor => println!("Slow motion at 1/{} speed", divisor),
+        },
+        Hotkey::SoftReset => {
+            cpu.soft_reset();
+            if let Some(debugger) = debugger {
+                debugger.reset_history();
+            }
+            println!("Soft reset")
+        }
+        Hotkey::HardReset => match game.boot() {
+            Ok(mut fresh) => {
+                fresh.set_trace(cpu.take_trace());
+                std::mem::swap(fresh.watchpoints_mut(), cpu.watchpoints_mut());
+                *cpu = fresh;
+                if let Some(debugger) = debugger {
+                    debugger.reset_history();
+                }
+                println!("Hard reset")
+            }
+            Err(error) => println!("{}", error),
+        },
+    }
+}
 
-    let mut cpu_counter = match FrequencyTracker::from_str(cpu_freq) {
-        Ok(counter) => counter,
-        Err(error) => return Err(error),
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let instructions_per_frame = parse_instructions_per_frame(matches)?;
+    let profile: Profile = matches.value_of("quirks").unwrap_or(QUIRKS).parse()?;
+    let fault_policy = match matches.value_of("on_fault") {
+        Some(action) => FaultPolicy::uniform(action.parse()?),
+        None => FaultPolicy::default(),
     };
 
-    let mut io_counter = match FrequencyTracker::from_str(io_freq) {
-        Ok(counter) => counter,
-        Err(error) => return Err(error),
+    let mut rewind = parse_rewind(matches)?;
+    let mut speed = Speed::new(parse_fast_forward(matches)?);
+    let mut scheduler = Scheduler::new(instructions_per_frame);
+    let mut debugger = if matches.is_present("debug") || matches.is_present("gdb") {
+        Some(Debugger::new(instructions_per_frame))
+    } else {
+        None
     };
 
     let rom_path = matches.value_of("ROM").unwrap();
@@ -55,42 +312,162 @@ fn run(matches: &ArgMatches) -> Result<(), String> {
         Ok(rom) => rom,
         Err(error) => return Err(error.to_string()),
     };
-    let mut cpu = CPU::init(&rom);
+    let game = Game {
+        path: Path::new(rom_path),
+        hash: rom::hash(&rom),
+        rom,
+        profile,
+        fault_policy,
+        seed: matches.value_of("seed").map(parse_seed).transpose()?,
+    };
+    let keymap = load_keymap(matches, game.path)?;
+    let mut slot = 0;
+    let mut cpu = game.boot()?;
+    cpu.set_trace(parse_trace(matches)?);
+
+    let mut gdb = match matches.value_of("gdb") {
+        Some(address) => Some(accept_gdb(address)?),
+        None => None,
+    };
 
     let context = Context::new();
     let mut display = Display::new(&context);
     let mut buzzer = Buzzer::new(&context);
-    let mut keyboard = Keyboard::new(&context);
+    let mut keyboard = Keyboard::new(&context, &keymap)?;
+    let mut debug_window = if matches.is_present("debug_window") {
+        Some(DebugWindow::new(&context, display.position_right(), &cpu))
+    } else {
+        None
+    };
     let mut renderer = Renderer::new(&mut display);
+    let labels = keyboard.labels();
+    renderer.set_hints(hints(&keymap, &labels));
+    print!("{}", keymap::format_keyboard(&labels));
 
     renderer.reset();
-    while let Ok(state) = keyboard.poll() {
-        if io_counter.is_burnt() {
-            cpu.decrement_delay();
-            cpu.decrement_sound();
-            renderer.render(cpu.get_vram());
-
-            if cpu.beeping() {
-                buzzer.play()
-            } else {
-                buzzer.pause()
+    while let Ok(input) = keyboard.poll() {
+        for &hotkey in input.hotkeys.iter() {
+            handle_hotkey(
+                hotkey,
+                &mut cpu,
+                debugger.as_mut(),
+                &mut speed,
+                &game,
+                &mut slot,
+                &mut renderer,
+            );
+        }
+        speed.set_fast_forwarding(input.fast_forwarding);
+
+        if let (Some(window), Some(scroll)) = (debug_window.as_mut(), input.scroll) {
+            window.scroll(scroll, &cpu);
+        }
+
+        if cpu.halted() {
+            if let Some(ref mut gdb) = gdb {
+                gdb.report_exit().map_err(|error| error.to_string())?;
+            }
+            break;
+        }
+
+        if gdb.as_ref().is_some_and(GdbStub::detached) {
+            println!("GDB detached");
+            gdb = None;
+        }
+
+        // The emulation is frozen while the debugger waits for commands,
+        // the window being redrawn after each of them.
+        if let Some(ref mut debugger) = debugger {
+            if debugger.paused() {
+                buzzer.pause();
+                let action = match gdb {
+                    Some(ref mut gdb) => gdb
+                        .report_stop(debugger, &cpu)
+                        .and_then(|_| gdb.serve(debugger, &mut cpu, &input.keypad))
+                        .map_err(|error| format!("GDB: {}", error))?,
+                    None => prompt(debugger, &mut cpu, &input.keypad),
+                };
+                if action == Action::Quit {
+                    break;
+                }
+                renderer.render(cpu.get_vram());
+                if let Some(ref mut window) = debug_window {
+                    window.update(&cpu);
+                }
+                continue;
+            }
+
+            if let Some(ref mut gdb) = gdb {
+                if gdb
+                    .poll_interrupt()
+                    .map_err(|error| format!("GDB: {}", error))?
+                {
+                    debugger.pause();
+                }
+            }
+        }
+
+        // A halted CPU keeps its last frame on screen until the user quits,
+        // or rewinds to before the fault.
+        if input.rewinding {
+            if let Some(snapshot) = rewind.pop() {
+                cpu.restore(&snapshot);
+                if let Some(ref mut debugger) = debugger {
+                    debugger.reset_history();
+                }
             }
+        } else if cpu.fault().is_none() {
+            for _ in 0..speed.frames() {
+                rewind.push(&cpu.snapshot());
+
+                let result = match debugger {
+                    Some(ref mut debugger) => debugger.run_frame(&mut cpu, &input.keypad),
+                    None => scheduler.run_frame(&mut cpu, &input.keypad),
+                };
+                if let Some(fault) = cpu.take_ignored_fault() {
+                    println!("Ignored CPU fault: {}, later ones are not reported", fault);
+                }
+                if let Err(fault) = result {
+                    println!("CPU fault: {}", fault);
+                    for line in inspector::registers(&cpu) {
+                        println!("{}", line);
+                    }
+                    break;
+                }
+                if let Some(ref mut debugger) = debugger {
+                    print!("{}", debugger.report());
+                    if debugger.paused() {
+                        if gdb.is_none() {
+                            print!("Paused at {}", debugger.current(&cpu));
+                        }
+                        break;
+                    }
+                }
+                if cpu.halted() {
+                    break;
+                }
+            }
+        }
 
-            io_counter.reset()
+        renderer.render(cpu.get_vram());
+        if let Some(ref mut window) = debug_window {
+            window.update(&cpu);
         }
 
-        if cpu_counter.is_burnt() {
-            cpu.tick(&state);
-            cpu_counter.reset();
+        if cpu.beeping() && !speed.paused() {
+            buzzer.set_pattern(cpu.audio_pattern(), cpu.pitch());
+            buzzer.play()
         } else {
-            // This can be safely assume for a 500hz CPU block and 60HZ display refresh rate.
-            thread::sleep(time::Duration::from_millis(
-                cpu_counter.burnt_duration() as u64
-            ));
+            buzzer.pause()
         }
+
+        scheduler.wait_frame();
     }
 
-    Ok(())
+    match cpu.take_trace() {
+        Some(trace) => trace.finish().map_err(|error| error.to_string()),
+        None => Ok(()),
+    }
 }
 
 pub fn main() {
@@ -108,19 +485,104 @@ pub fn main() {
                 .short("c")
                 .long("cpu-freq")
                 .value_name("cpu_freq")
-                .help("Set the frequency of the CPU clock in Hz"),
+                .conflicts_with("ipf")
+                .help("Set the frequency of the CPU clock in Hz, rounded to whole instructions per frame"),
+        )
+        .arg(
+            Arg::with_name("ipf")
+                .short("p")
+                .long("ipf")
+                .value_name("ipf")
+                .help("Set the number of instructions executed per 60 Hz frame"),
+        )
+        .arg(
+            Arg::with_name("quirks")
+                .short("q")
+                .long("quirks")
+                .value_name("quirks")
+                .possible_values(&PROFILES)
+                .help("Select the behavior of ambiguous instructions"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .short("s")
+                .long("seed")
+                .value_name("seed")
+                .help("Seed the random number generator for reproducible runs"),
         )
         .arg(
-            Arg::with_name("io_freq")
-                .short("i")
-                .long("io-freq")
-                .value_name("io_freq")
-                .help("Set the display and buzzer refresh rate in Hz"),
+            Arg::with_name("on_fault")
+                .long("on-fault")
+                .value_name("on_fault")
+                .possible_values(&ACTIONS)
+                .help("Halt, ignore or wrap around on CPU faults"),
+        )
+        .arg(
+            Arg::with_name("rewind")
+                .long("rewind")
+                .value_name("seconds")
+                .help("Set how far back holding Backspace rewinds (30 by default, 0 disables)"),
+        )
+        .arg(
+            Arg::with_name("keymap")
+                .long("keymap")
+                .value_name("file")
+                .help("Read the keys bound to the keypad from a file"),
+        )
+        .arg(
+            Arg::with_name("fast_forward")
+                .long("fast-forward")
+                .value_name("speed")
+                .help("Set how many times faster holding Tab runs (4 by default)"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .value_name("file")
+                .help("Write a line per executed instruction to a file"),
+        )
+        .arg(
+            Arg::with_name("trace_pc")
+                .long("trace-pc")
+                .value_name("range")
+                .requires("trace")
+                .help("Only trace instructions between two hex addresses, as 200-2ff"),
+        )
+        .arg(
+            Arg::with_name("trace_class")
+                .long("trace-class")
+                .value_name("class")
+                .requires("trace")
+                .multiple(true)
+                .use_delimiter(true)
+                .possible_values(&CLASSES)
+                .help("Only trace instructions of these opcode classes"),
+        )
+        .arg(
+            Arg::with_name("trace_memory")
+                .long("trace-memory")
+                .requires("trace")
+                .help("Add a hash of the memory to every trace line"),
+        )
+        .arg(
+            Arg::with_name("debug")
+                .long("debug")
+                .help("Start paused in a debugger reading commands from stdin"),
+        )
+        .arg(
+            Arg::with_name("debug_window")
+                .long("debug-window")
+                .help("Show the registers, stack, disassembly and memory in a second window"),
+        )
+        .arg(
+            Arg::with_name("gdb")
+                .long("gdb")
+                .value_name("address")
+                .conflicts_with("debug")
+                .help("Wait for GDB on a TCP host:port or a unix:<path> socket"),
         )
         .get_matches();
 
-    print_keyboard();
-
     if let Err(error) = run(&matches) {
         println!("Error: {}", error)
     }
@@ -0,0 +1,192 @@
+use std::str::FromStr;
+
+use crate::{CHIP8_MEMORY_SIZE, MEMORY_SIZE};
+
+/// How `LD [I], Vx` and `LD Vx, [I]` leave the I register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexIncrement {
+    /// I is left untouched.
+    Unchanged,
+    /// I is incremented by x.
+    ByX,
+    /// I is incremented by x + 1, pointing past the last register.
+    ByXPlusOne,
+}
+
+/// Interpretation of the CHIP-8 instructions whose behavior differs between
+/// interpreters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    /// `SHR`/`SHL` shift Vy into Vx instead of shifting Vx in place.
+    pub shift_vy: bool,
+    /// Effect of `LD [I], Vx` and `LD Vx, [I]` on I.
+    pub index_increment: IndexIncrement,
+    /// `Bnnn` jumps to nnn + Vx instead of nnn + V0.
+    pub jump_vx: bool,
+    /// `OR`, `AND` and `XOR` reset VF to 0.
+    pub vf_reset: bool,
+    /// `DRW` clips sprites at the screen edges instead of wrapping them.
+    pub clip_sprites: bool,
+    /// `DRW` waits for the next vertical blank, drawing at most once a frame.
+    pub display_wait: bool,
+}
+
+pub const PROFILES: [&str; 5] = ["crust", "vip", "chip48", "schip", "xochip"];
+
+impl Quirks {
+    /// Behavior of crust before quirks became configurable.
+    pub fn crust() -> Self {
+        Self {
+            shift_vy: false,
+            index_increment: IndexIncrement::Unchanged,
+            jump_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+        }
+    }
+
+    /// The original COSMAC VIP interpreter.
+    pub fn cosmac_vip() -> Self {
+        Self {
+            shift_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators.
+    pub fn chip48() -> Self {
+        Self {
+            shift_vy: false,
+            index_increment: IndexIncrement::ByX,
+            jump_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1.
+    pub fn super_chip() -> Self {
+        Self {
+            shift_vy: false,
+            index_increment: IndexIncrement::Unchanged,
+            jump_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    /// XO-CHIP, as implemented by Octo.
+    pub fn xo_chip() -> Self {
+        Self {
+            shift_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::crust()
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(profile: &str) -> Result<Self, Self::Err> {
+        profile.parse::<Profile>().map(|profile| profile.quirks)
+    }
+}
+
+/// Machine emulated by a profile: its quirks and its memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Profile {
+    pub quirks: Quirks,
+    /// Bytes of addressable memory, accesses past it being faults.
+    pub memory_size: usize,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            quirks: Quirks::default(),
+            memory_size: CHIP8_MEMORY_SIZE,
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(profile: &str) -> Result<Self, Self::Err> {
+        let (quirks, memory_size) = match profile {
+            "crust" => (Quirks::crust(), CHIP8_MEMORY_SIZE),
+            "vip" => (Quirks::cosmac_vip(), CHIP8_MEMORY_SIZE),
+            "chip48" => (Quirks::chip48(), CHIP8_MEMORY_SIZE),
+            "schip" => (Quirks::super_chip(), CHIP8_MEMORY_SIZE),
+            "xochip" => (Quirks::xo_chip(), MEMORY_SIZE),
+            _ => {
+                return Err(format!(
+                    "Unknown quirks profile '{}', expected one of: {}.",
+                    profile,
+                    PROFILES.join(", ")
+                ))
+            }
+        };
+
+        Ok(Self {
+            quirks,
+            memory_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod quirks_test {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        assert_eq!(Quirks::default(), Quirks::crust());
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("vip".parse::<Quirks>(), Ok(Quirks::cosmac_vip()));
+        assert_eq!("chip48".parse::<Quirks>(), Ok(Quirks::chip48()));
+        assert_eq!("schip".parse::<Quirks>(), Ok(Quirks::super_chip()));
+        assert_eq!("xochip".parse::<Quirks>(), Ok(Quirks::xo_chip()));
+        assert!("megachip".parse::<Quirks>().is_err());
+    }
+
+    #[test]
+    fn test_profiles_parse() {
+        for profile in PROFILES.iter() {
+            assert!(profile.parse::<Quirks>().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_profile_memory() {
+        assert_eq!(Profile::default().memory_size, CHIP8_MEMORY_SIZE);
+        assert_eq!(
+            "schip".parse::<Profile>().unwrap().memory_size,
+            CHIP8_MEMORY_SIZE
+        );
+
+        let profile: Profile = "xochip".parse().unwrap();
+        assert_eq!(profile.quirks, Quirks::xo_chip());
+        assert_eq!(profile.memory_size, MEMORY_SIZE);
+    }
+}
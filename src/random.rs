@@ -0,0 +1,67 @@
+use rand;
+
+/// SplitMix64 generator backing `RND`.
+///
+/// The whole state is one integer, so a seed is enough to reproduce a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeds the generator from the operating system.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random::<u64>())
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+#[cfg(test)]
+mod random_test {
+    use super::*;
+
+    #[test]
+    fn test_known_sequence() {
+        let mut random = Random::new(1234567);
+
+        assert_eq!(random.next_u64(), 0x599E_D017_FB08_FC85);
+        assert_eq!(random.next_u64(), 0x2C73_F084_5854_0FA5);
+    }
+
+    #[test]
+    fn test_same_seed_same_bytes() {
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+
+        for _ in 0..64 {
+            assert_eq!(first.next_u8(), second.next_u8());
+        }
+        assert_eq!(first.state(), second.state());
+    }
+}
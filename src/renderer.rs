@@ -1,34 +1,107 @@
-use crate::cpu::VRAM;
+use sdl2::rect::Rect;
+
 use crate::display::{Display, DisplayColor};
+use crate::text::Page;
+use crust::cpu::VRAM;
+use crust::keypad::LAYOUT;
+
+// Pixels of the glyphs of the keypad hints, and spacing around the keys.
+const HINT_SCALE: usize = 3;
+const HINT_MARGIN: usize = 4;
+// Inks of the keypad hints.
+const DIGIT: usize = 0;
+const LABEL: usize = 1;
 
 pub struct Renderer<'a> {
     display: &'a mut Display,
+    // Label of the host key of each CHIP-8 key, shown over the screen.
+    hints: Vec<String>,
+    show_hints: bool,
 }
 
 impl<'a> Renderer<'a> {
     pub fn new(display: &'a mut Display) -> Self {
-        Renderer { display: display }
+        Renderer {
+            display,
+            hints: Vec::new(),
+            show_hints: false,
+        }
     }
 
     pub fn reset(&mut self) {
         self.display.clear_screen();
     }
 
-    fn get_color(pixel: bool) -> DisplayColor {
-        if pixel {
-            DisplayColor::Black
-        } else {
-            DisplayColor::White
+    /// Labels of the keypad shown by `toggle_hints()`, by CHIP-8 key.
+    pub fn set_hints(&mut self, labels: Vec<String>) {
+        self.hints = labels
+    }
+
+    /// Shows or hides the keypad over the screen.
+    pub fn toggle_hints(&mut self) {
+        self.show_hints = !self.show_hints
+    }
+
+    fn get_color(pixel: u8) -> DisplayColor {
+        match pixel {
+            0x1 => DisplayColor::Black,
+            0x2 => DisplayColor::LightGray,
+            0x3 => DisplayColor::DarkGray,
+            _ => DisplayColor::White,
         }
     }
 
     pub fn render(&mut self, vram: &VRAM) {
-        for (j, &line) in vram.iter().enumerate() {
+        self.display.set_resolution(vram.width(), vram.height());
+
+        for (j, line) in vram.lines().enumerate() {
             for (i, &pixel) in line.iter().enumerate() {
                 self.display
                     .draw_pixel(i as i32, j as i32, Self::get_color(pixel))
             }
         }
+        if self.show_hints && !self.hints.is_empty() {
+            self.draw_hints();
+        }
         self.display.draw()
     }
+
+    fn draw_hints(&mut self) {
+        let (width, height) = self.display.size();
+        let (key_width, key_height) = (width as usize / 4, height as usize / 4);
+        let mut page = Page::new(HINT_SCALE, 2);
+        let (cell_width, cell_height) = page.cell();
+        let mut keys = Vec::new();
+
+        for (row, line) in LAYOUT.iter().enumerate() {
+            for (column, &key) in line.iter().enumerate() {
+                let (x, y) = (column * key_width, row * key_height);
+                keys.push(Rect::new(
+                    (x + HINT_MARGIN) as i32,
+                    (y + HINT_MARGIN) as i32,
+                    (key_width - 2 * HINT_MARGIN) as u32,
+                    (key_height - 2 * HINT_MARGIN) as u32,
+                ));
+
+                let left = x + 3 * HINT_MARGIN;
+                let label: String = self.hints[key as usize]
+                    .chars()
+                    .take((key_width - 6 * HINT_MARGIN) / cell_width)
+                    .collect();
+                page.text_at(left, y + 3 * HINT_MARGIN, &format!("{:X}", key), DIGIT);
+                page.text_at(
+                    left,
+                    y + key_height - 2 * HINT_MARGIN - cell_height,
+                    &label,
+                    LABEL,
+                );
+            }
+        }
+
+        self.display.fill_rects(&keys, DisplayColor::DarkGray);
+        self.display
+            .fill_rects(page.rects(DIGIT), DisplayColor::LightGray);
+        self.display
+            .fill_rects(page.rects(LABEL), DisplayColor::White);
+    }
 }
@@ -0,0 +1,247 @@
+use std::collections::VecDeque;
+
+use crate::snapshot::Snapshot;
+
+/// Snapshots kept by default: one per frame for 30 seconds.
+pub const DEFAULT_CAPACITY: usize = 30 * crate::FRAME_RATE as usize;
+/// Snapshots stored as deltas after each full one by default.
+pub const DEFAULT_KEYFRAME_INTERVAL: usize = 60;
+
+// Unchanged bytes between two changed spans below which they are merged.
+const MERGE_GAP: usize = 8;
+
+/// Bytes of a snapshot differing from its keyframe, as (offset, bytes) spans.
+struct Delta {
+    spans: Vec<(usize, Vec<u8>)>,
+}
+
+impl Delta {
+    fn new(keyframe: &[u8], bytes: &[u8]) -> Self {
+        let mut spans: Vec<(usize, Vec<u8>)> = Vec::new();
+        let mut offset = 0;
+
+        while offset < bytes.len() {
+            if bytes[offset] == keyframe[offset] {
+                offset += 1;
+                continue;
+            }
+
+            let start = offset;
+            while offset < bytes.len() && bytes[offset] != keyframe[offset] {
+                offset += 1;
+            }
+
+            match spans.last_mut() {
+                Some((previous, span)) if start - (*previous + span.len()) < MERGE_GAP => {
+                    span.extend_from_slice(&bytes[*previous + span.len()..offset])
+                }
+                _ => spans.push((start, bytes[start..offset].to_vec())),
+            }
+        }
+
+        Self { spans }
+    }
+
+    fn apply(&self, keyframe: &[u8]) -> Vec<u8> {
+        let mut bytes = keyframe.to_vec();
+
+        for (offset, span) in self.spans.iter() {
+            bytes[*offset..*offset + span.len()].copy_from_slice(span);
+        }
+        bytes
+    }
+
+    fn size(&self) -> usize {
+        self.spans.iter().map(|(_, span)| span.len()).sum()
+    }
+}
+
+/// Full snapshot followed by deltas against it.
+struct Segment {
+    keyframe: Vec<u8>,
+    deltas: Vec<Delta>,
+}
+
+impl Segment {
+    fn len(&self) -> usize {
+        1 + self.deltas.len()
+    }
+}
+
+/// Ring buffer of the most recent snapshots, for running the program
+/// backwards.
+///
+/// Every `keyframe_interval`th snapshot is kept whole and the others as
+/// deltas against it. The oldest snapshots are dropped a segment at a time,
+/// so up to `keyframe_interval - 1` snapshots more than `capacity` are kept.
+pub struct Rewind {
+    capacity: usize,
+    keyframe_interval: usize,
+    segments: VecDeque<Segment>,
+    len: usize,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, keyframe_interval: usize) -> Self {
+        assert!(keyframe_interval > 0, "keyframe interval must be positive");
+
+        Self {
+            capacity,
+            keyframe_interval,
+            segments: VecDeque::new(),
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.len = 0;
+    }
+
+    /// Bytes of snapshot data held.
+    pub fn size(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| {
+                segment.keyframe.len() + segment.deltas.iter().map(Delta::size).sum::<usize>()
+            })
+            .sum()
+    }
+
+    pub fn push(&mut self, snapshot: &Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let bytes = snapshot.to_bytes();
+        let keyframe_interval = self.keyframe_interval;
+
+        match self.segments.back_mut() {
+            // A resolution change alters the size, needing a new keyframe.
+            Some(segment)
+                if segment.len() < keyframe_interval && segment.keyframe.len() == bytes.len() =>
+            {
+                let delta = Delta::new(&segment.keyframe, &bytes);
+                segment.deltas.push(delta)
+            }
+            _ => self.segments.push_back(Segment {
+                keyframe: bytes,
+                deltas: Vec::new(),
+            }),
+        }
+        self.len += 1;
+
+        while let Some(front) = self.segments.front() {
+            if self.len - front.len() < self.capacity {
+                break;
+            }
+            self.len -= front.len();
+            self.segments.pop_front();
+        }
+    }
+
+    /// Removes and returns the most recent snapshot.
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        let segment = self.segments.back_mut()?;
+        let bytes = match segment.deltas.pop() {
+            Some(delta) => delta.apply(&segment.keyframe),
+            None => self.segments.pop_back()?.keyframe,
+        };
+        self.len -= 1;
+
+        Some(Snapshot::from_bytes(&bytes).expect("rewind snapshots are well formed"))
+    }
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_KEYFRAME_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod rewind_test {
+    use super::*;
+    use crate::cpu::CPU;
+    use crate::keypad::State;
+    use crate::MEMORY_SIZE;
+
+    fn counter_cpu() -> CPU {
+        // ADD V0, 1; LD [I], V0; ADD I, V0; JP 0x200
+        let mut cpu = CPU::init(&[0x70, 0x01, 0xF0, 0x55, 0xF0, 0x1E, 0x12, 0x00]);
+        // I runs past 4 KiB.
+        cpu.set_memory_size(MEMORY_SIZE).unwrap();
+        cpu.set_seed(0);
+        cpu
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut cpu = counter_cpu();
+        let mut rewind = Rewind::new(100, 4);
+        let mut snapshots = Vec::new();
+
+        for _ in 0..10 {
+            snapshots.push(cpu.snapshot());
+            rewind.push(&cpu.snapshot());
+            cpu.run_frame(&State::new(), 8).unwrap();
+        }
+        assert_eq!(rewind.len(), 10);
+
+        while let Some(snapshot) = rewind.pop() {
+            assert_eq!(snapshot, snapshots.pop().unwrap());
+        }
+        assert!(snapshots.is_empty());
+        assert!(rewind.is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut cpu = counter_cpu();
+        let mut rewind = Rewind::new(10, 4);
+
+        for _ in 0..25 {
+            rewind.push(&cpu.snapshot());
+            cpu.run_frame(&State::new(), 8).unwrap();
+        }
+        assert!(rewind.len() >= 10 && rewind.len() < 14);
+
+        let newest = rewind.pop().unwrap();
+        cpu.restore(&newest);
+        assert_eq!(cpu.v()[0], 24 * 2);
+    }
+
+    #[test]
+    fn test_deltas_are_small() {
+        let mut cpu = counter_cpu();
+        let mut rewind = Rewind::new(60, 60);
+
+        for _ in 0..60 {
+            rewind.push(&cpu.snapshot());
+            cpu.run_frame(&State::new(), 8).unwrap();
+        }
+
+        let full = cpu.snapshot().to_bytes().len();
+        assert!(rewind.size() < 2 * full);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut rewind = Rewind::new(0, 1);
+
+        rewind.push(&counter_cpu().snapshot());
+        assert!(rewind.pop().is_none());
+    }
+}
@@ -2,27 +2,35 @@ use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 
+use crate::hash::fnv1a;
 use crate::ROM_SIZE;
 
-pub type RomBuffer = [u8; ROM_SIZE];
+/// Contents of a ROM file, up to `ROM_SIZE` bytes.
+pub type RomBuffer = Vec<u8>;
 
 pub fn load_rom(path: &str) -> io::Result<RomBuffer> {
     let mut handle = File::open(path)?;
-    let mut buffer: RomBuffer = [0; ROM_SIZE];
+    let mut buffer = Vec::new();
 
-    handle.read(&mut buffer)?;
+    handle.read_to_end(&mut buffer)?;
+    buffer.truncate(ROM_SIZE);
 
     Ok(buffer)
 }
 
+/// Identifies a ROM, for instance to match it with its save states.
+pub fn hash(rom: &[u8]) -> u64 {
+    fnv1a(rom.iter())
+}
+
 pub struct Reader<'a> {
     cur: usize,
-    rom: &'a RomBuffer,
+    rom: &'a [u8],
 }
 
 impl<'a> Reader<'a> {
-    pub fn new(rom: &'a RomBuffer) -> Self {
-        Self { cur: 0, rom: rom }
+    pub fn new(rom: &'a [u8]) -> Self {
+        Self { cur: 0, rom }
     }
 }
 
@@ -30,7 +38,7 @@ impl<'a> Iterator for Reader<'a> {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cur < ROM_SIZE {
+        if self.cur < ROM_SIZE.min(self.rom.len()) {
             let value = self.rom[self.cur];
             self.cur += 1;
             Some(value)
@@ -60,7 +68,7 @@ mod rom_test {
 
     #[test]
     fn test_new_rom_reader() {
-        let rom: RomBuffer = [0; ROM_SIZE];
+        let rom: RomBuffer = vec![0; ROM_SIZE];
         let reader = Reader::new(&rom);
 
         assert_eq!(reader.cur, 0);
@@ -68,10 +76,10 @@ mod rom_test {
 
     #[test]
     fn test_iterate_rom() {
-        let mut rom: RomBuffer = [0; ROM_SIZE];
+        let mut rom: RomBuffer = vec![0; ROM_SIZE];
 
         for (i, &elem) in [0x01, 0x02, 0x03, 0x04].iter().enumerate() {
-            rom[i as usize] = elem;
+            rom[i] = elem;
         }
 
         let mut reader = Reader::new(&rom);
@@ -0,0 +1,202 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::snapshot::Snapshot;
+
+/// First bytes of every save state file.
+pub const MAGIC: &[u8; 8] = b"CRUSTSAV";
+/// Version of the format written, bumped on any layout change.
+pub const VERSION: u16 = 2;
+/// Number of save state slots per ROM.
+pub const SLOTS: u8 = 10;
+
+/// Error raised while loading or saving a state.
+#[derive(Debug)]
+pub enum SaveStateError {
+    Io(io::Error),
+    /// Not a save state file.
+    BadMagic,
+    /// Save state written by a newer or older, incompatible crust.
+    UnsupportedVersion(u16),
+    /// Save state made for another ROM.
+    RomMismatch {
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveStateError::Io(error) => write!(f, "{}", error),
+            SaveStateError::BadMagic => write!(f, "not a crust save state"),
+            SaveStateError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported save state version {}, expected {}",
+                version, VERSION
+            ),
+            SaveStateError::RomMismatch { expected, found } => write!(
+                f,
+                "save state made for another ROM (hash {:016x}, expected {:016x})",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for SaveStateError {}
+
+impl From<io::Error> for SaveStateError {
+    fn from(error: io::Error) -> Self {
+        SaveStateError::Io(error)
+    }
+}
+
+/// Snapshot of a CPU along with the hash of the ROM it was running.
+///
+/// The file holds `MAGIC`, the version and the ROM hash in little endian,
+/// followed by the snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveState {
+    rom_hash: u64,
+    snapshot: Snapshot,
+}
+
+impl SaveState {
+    pub fn new(rom_hash: u64, snapshot: Snapshot) -> Self {
+        Self { rom_hash, snapshot }
+    }
+
+    pub fn rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        output.write_all(MAGIC)?;
+        output.write_all(&VERSION.to_le_bytes())?;
+        output.write_all(&self.rom_hash.to_le_bytes())?;
+        self.snapshot.write(output)
+    }
+
+    /// Reads a save state, rejecting it unless it was made for the ROM
+    /// hashed to `rom_hash`.
+    pub fn read<R: Read>(input: &mut R, rom_hash: u64) -> Result<Self, SaveStateError> {
+        let mut magic = [0; 8];
+        input
+            .read_exact(&mut magic)
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::UnexpectedEof => SaveStateError::BadMagic,
+                _ => SaveStateError::Io(error),
+            })?;
+        if &magic != MAGIC {
+            return Err(SaveStateError::BadMagic);
+        }
+
+        let mut version = [0; 2];
+        input.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != VERSION {
+            return Err(SaveStateError::UnsupportedVersion(version));
+        }
+
+        let mut found = [0; 8];
+        input.read_exact(&mut found)?;
+        let found = u64::from_le_bytes(found);
+        if found != rom_hash {
+            return Err(SaveStateError::RomMismatch {
+                expected: rom_hash,
+                found,
+            });
+        }
+
+        Ok(Self::new(found, Snapshot::read(input)?))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SaveStateError> {
+        let mut output = BufWriter::new(File::create(path)?);
+        self.write(&mut output)?;
+        output.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path, rom_hash: u64) -> Result<Self, SaveStateError> {
+        Self::read(&mut BufReader::new(File::open(path)?), rom_hash)
+    }
+}
+
+/// File of save state `slot` for the ROM at `rom_path`, next to the ROM.
+pub fn slot_path(rom_path: &Path, slot: u8) -> PathBuf {
+    let mut path = rom_path.as_os_str().to_owned();
+    path.push(format!(".state{}", slot));
+
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod savestate_test {
+    use super::*;
+    use crate::cpu::CPU;
+
+    fn save_state() -> SaveState {
+        SaveState::new(0x1234, CPU::new().snapshot())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = save_state();
+        let mut bytes = Vec::new();
+        state.write(&mut bytes).unwrap();
+
+        assert_eq!(&bytes[..8], MAGIC);
+        assert_eq!(SaveState::read(&mut &bytes[..], 0x1234).unwrap(), state);
+    }
+
+    #[test]
+    fn test_rom_mismatch() {
+        let mut bytes = Vec::new();
+        save_state().write(&mut bytes).unwrap();
+
+        match SaveState::read(&mut &bytes[..], 0x5678) {
+            Err(SaveStateError::RomMismatch { expected, found }) => {
+                assert_eq!((expected, found), (0x5678, 0x1234))
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_bad_header() {
+        match SaveState::read(&mut &b"CRUST"[..], 0) {
+            Err(SaveStateError::BadMagic) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+
+        let mut bytes = Vec::new();
+        save_state().write(&mut bytes).unwrap();
+        bytes[8] = 0xFF;
+        match SaveState::read(&mut &bytes[..], 0x1234) {
+            Err(SaveStateError::UnsupportedVersion(0x00FF)) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_save_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = slot_path(&dir.path().join("pong.ch8"), 3);
+        assert_eq!(path, dir.path().join("pong.ch8.state3"));
+
+        let state = save_state();
+        state.save(&path).unwrap();
+        assert_eq!(SaveState::load(&path, 0x1234).unwrap(), state);
+    }
+}
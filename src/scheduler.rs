@@ -0,0 +1,90 @@
+use crate::clock::{Clock, SystemClock};
+use crate::cpu::CPU;
+use crate::fault::CpuFault;
+use crate::keypad::State;
+use crate::tools::FrequencyTracker;
+use crate::FRAME_RATE;
+
+/// Paces the emulation in frames of `FRAME_RATE` Hz.
+///
+/// Emulated time only depends on the number of frames run, so with a
+/// `VirtualClock` a run goes as fast as the host allows and stays exact.
+pub struct Scheduler<C: Clock = SystemClock> {
+    tracker: FrequencyTracker<C>,
+    instructions_per_frame: usize,
+    frames: u64,
+}
+
+impl Scheduler<SystemClock> {
+    pub fn new(instructions_per_frame: usize) -> Self {
+        Self::with_clock(instructions_per_frame, SystemClock::new())
+    }
+}
+
+impl<C: Clock> Scheduler<C> {
+    pub fn with_clock(instructions_per_frame: usize, clock: C) -> Self {
+        Self {
+            tracker: FrequencyTracker::with_clock(FRAME_RATE as f64, clock),
+            instructions_per_frame,
+            frames: 0,
+        }
+    }
+
+    pub fn instructions_per_frame(&self) -> usize {
+        self.instructions_per_frame
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions_per_frame: usize) {
+        self.instructions_per_frame = instructions_per_frame
+    }
+
+    /// Number of frames run so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn clock(&self) -> &C {
+        self.tracker.clock()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.tracker.dropped()
+    }
+
+    /// Runs one frame of `cpu`.
+    pub fn run_frame(&mut self, cpu: &mut CPU, input: &State) -> Result<(), CpuFault> {
+        self.frames += 1;
+
+        cpu.run_frame(input, self.instructions_per_frame)
+    }
+
+    /// Waits on the clock for the end of the current frame, returning the
+    /// number of frames dropped to catch up.
+    pub fn wait_frame(&mut self) -> u64 {
+        self.tracker.wait();
+        self.tracker.reset()
+    }
+}
+
+#[cfg(test)]
+mod scheduler_test {
+    use super::*;
+    use crate::clock::VirtualClock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_frames() {
+        // ADD V0, 1; JP 0x200
+        let mut cpu = CPU::init(&[0x70, 0x01, 0x12, 0x00]);
+        let mut scheduler = Scheduler::with_clock(10, VirtualClock::new());
+
+        for _ in 0..FRAME_RATE {
+            scheduler.run_frame(&mut cpu, &State::new()).unwrap();
+            assert_eq!(scheduler.wait_frame(), 0);
+        }
+
+        assert_eq!(scheduler.frames(), FRAME_RATE);
+        assert_eq!(scheduler.clock().now(), Duration::from_secs(1));
+        assert_eq!(cpu.v()[0], (FRAME_RATE * 5) as u8);
+    }
+}
@@ -0,0 +1,161 @@
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::vram::VRAM;
+
+/// File formats the framebuffer can be dumped to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    /// One character per pixel, one line per row.
+    Ascii,
+    /// Binary portable bitmap, lit on any plane being black.
+    Pbm,
+    /// 8-bit grayscale PNG with the colours of the emulator window.
+    Png,
+    /// `VRAM::fingerprint()` in hexadecimal, as stored for golden values.
+    Hash,
+}
+
+pub const FORMATS: [&str; 4] = ["ascii", "pbm", "png", "hash"];
+
+/// ASCII character of each pixel value, by lit planes.
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '*'];
+
+/// Gray level of each pixel value, matching the emulator window.
+const GRAY_PIXELS: [u8; 4] = [255, 0, 170, 85];
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "ascii" => Ok(ImageFormat::Ascii),
+            "pbm" => Ok(ImageFormat::Pbm),
+            "png" => Ok(ImageFormat::Png),
+            "hash" => Ok(ImageFormat::Hash),
+            _ => Err(format!(
+                "Unknown image format '{}', expected one of: {}.",
+                format,
+                FORMATS.join(", ")
+            )),
+        }
+    }
+}
+
+pub fn write_vram<W: Write>(vram: &VRAM, format: ImageFormat, mut output: W) -> io::Result<()> {
+    match format {
+        ImageFormat::Ascii => write_ascii(vram, output),
+        ImageFormat::Pbm => write_pbm(vram, output),
+        ImageFormat::Png => write_png(vram, output),
+        ImageFormat::Hash => writeln!(output, "{:016x}", vram.fingerprint()),
+    }
+}
+
+fn write_ascii<W: Write>(vram: &VRAM, mut output: W) -> io::Result<()> {
+    for line in vram.lines() {
+        let text: String = line
+            .iter()
+            .map(|&pixel| ASCII_PIXELS[(pixel & 3) as usize])
+            .collect();
+        writeln!(output, "{}", text)?;
+    }
+
+    Ok(())
+}
+
+fn write_pbm<W: Write>(vram: &VRAM, mut output: W) -> io::Result<()> {
+    write!(output, "P4\n{} {}\n", vram.width(), vram.height())?;
+
+    for line in vram.lines() {
+        let row: Vec<u8> = line
+            .chunks(8)
+            .map(|pixels| {
+                pixels
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &pixel)| pixel != 0)
+                    .fold(0, |byte, (bit, _)| byte | 0x80 >> bit)
+            })
+            .collect();
+        output.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+fn write_png<W: Write>(vram: &VRAM, output: W) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(output, vram.width() as u32, vram.height() as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = vram
+        .lines()
+        .flatten()
+        .map(|&pixel| GRAY_PIXELS[(pixel & 3) as usize])
+        .collect();
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod screenshot_test {
+    use super::*;
+
+    #[test]
+    fn test_ascii() {
+        let mut vram = VRAM::default();
+        vram.set(0, 0, 1);
+        vram.set(2, 0, 3);
+
+        let mut output = Vec::new();
+        write_vram(&vram, ImageFormat::Ascii, &mut output).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(text.lines().count(), vram.height());
+        assert_eq!(&text[..4], "#.*.");
+    }
+
+    #[test]
+    fn test_pbm() {
+        let mut vram = VRAM::default();
+        vram.set(0, 0, 1);
+        vram.set(9, 0, 2);
+
+        let mut output = Vec::new();
+        write_vram(&vram, ImageFormat::Pbm, &mut output).unwrap();
+
+        let header = b"P4\n64 32\n";
+        assert_eq!(&output[..header.len()], header);
+        assert_eq!(output.len(), header.len() + 8 * 32);
+        assert_eq!(&output[header.len()..header.len() + 2], &[0x80, 0x40]);
+    }
+
+    #[test]
+    fn test_png() {
+        let mut output = Vec::new();
+        write_vram(&VRAM::default(), ImageFormat::Png, &mut output).unwrap();
+
+        assert_eq!(&output[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut output = Vec::new();
+        write_vram(&VRAM::default(), ImageFormat::Hash, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{:016x}\n", VRAM::default().fingerprint())
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("pbm".parse::<ImageFormat>(), Ok(ImageFormat::Pbm));
+        assert!("gif".parse::<ImageFormat>().is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+
+use crate::keypad::State;
+
+/// Keypad states held from a given frame until the next entry.
+///
+/// Each line is a frame number followed by the pressed keys as hex digits,
+/// or `-` for none:
+///
+/// ```text
+/// # press 5 on frame 10 for two frames
+/// 10 5
+/// 12 -
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputScript {
+    entries: Vec<(u64, State)>,
+}
+
+impl InputScript {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Keys pressed from `frame` on, replacing any entry for that frame.
+    pub fn press(&mut self, frame: u64, state: State) {
+        match self
+            .entries
+            .binary_search_by_key(&frame, |&(start, _)| start)
+        {
+            Ok(index) => self.entries[index].1 = state,
+            Err(index) => self.entries.insert(index, (frame, state)),
+        }
+    }
+
+    pub fn state_at(&self, frame: u64) -> State {
+        match self
+            .entries
+            .binary_search_by_key(&frame, |&(start, _)| start)
+        {
+            Ok(index) => self.entries[index].1,
+            Err(0) => State::new(),
+            Err(index) => self.entries[index - 1].1,
+        }
+    }
+}
+
+/// Parses pressed keys written as hex digits, `-` meaning no key.
+pub fn parse_keys(keys: &str) -> Result<State, String> {
+    if keys == "-" {
+        return Ok(State::new());
+    }
+
+    keys.chars()
+        .try_fold(State::new(), |state, key| match key.to_digit(16) {
+            Some(digit) => Ok(State::from_raw(state.as_raw() | 1 << digit)),
+            None => Err(format!("Unknown key '{}' in '{}'.", key, keys)),
+        })
+}
+
+impl FromStr for InputScript {
+    type Err = String;
+
+    fn from_str(script: &str) -> Result<Self, Self::Err> {
+        let mut result = Self::new();
+
+        for (number, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let entry = match (fields.next(), fields.next(), fields.next()) {
+                (Some(frame), Some(keys), None) => frame
+                    .parse::<u64>()
+                    .map_err(|_| format!("Couldn't parse value '{}' as a frame.", frame))
+                    .and_then(|frame| Ok((frame, parse_keys(keys)?))),
+                _ => Err("expected a frame and keys.".to_string()),
+            };
+
+            match entry {
+                Ok((frame, state)) => result.press(frame, state),
+                Err(error) => return Err(format!("Line {}: {}", number + 1, error)),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod script_test {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(parse_keys("-"), Ok(State::new()));
+        assert_eq!(parse_keys("5a"), Ok(State::from_raw(1 << 5 | 1 << 0xA)));
+        assert!(parse_keys("G").is_err());
+    }
+
+    #[test]
+    fn test_state_at() {
+        let script: InputScript = "# comment\n10 5\n\n12 -\n".parse().unwrap();
+
+        assert_eq!(script.state_at(0), State::new());
+        assert_eq!(script.state_at(10), State::from_raw(1 << 5));
+        assert_eq!(script.state_at(11), State::from_raw(1 << 5));
+        assert_eq!(script.state_at(12), State::new());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "1 5\nx 5".parse::<InputScript>(),
+            Err("Line 2: Couldn't parse value 'x' as a frame.".to_string())
+        );
+        assert!("1".parse::<InputScript>().is_err());
+        assert!("1 5 6".parse::<InputScript>().is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::io;
+use std::io::{Read, Write};
+
+use crate::audio::{Pattern, PATTERN_SIZE};
+use crate::cpu::{FLAG_REGISTERS, GENERAL_PURPOSE_REGISTERS, STACK_SIZE};
+use crate::vram::{Resolution, VRAM};
+use crate::MEMORY_SIZE;
+
+/// Complete state of a `CPU`, as taken by `CPU::snapshot()`.
+///
+/// Quirks and the fault policy are settings of the emulator rather than
+/// state of the program, and are not part of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub(crate) i: usize,
+    pub(crate) pc: u16,
+    pub(crate) sp: usize,
+    pub(crate) v: [u8; GENERAL_PURPOSE_REGISTERS],
+    pub(crate) dt: u8,
+    pub(crate) st: u8,
+    pub(crate) stack: [u16; STACK_SIZE],
+    pub(crate) ram: Vec<u8>,
+    pub(crate) vram: VRAM,
+    pub(crate) flags: [u8; FLAG_REGISTERS],
+    pub(crate) halted: bool,
+    pub(crate) plane: u8,
+    pub(crate) pattern: Pattern,
+    pub(crate) pitch: u8,
+    pub(crate) vblank: bool,
+    pub(crate) random: u64,
+    pub(crate) instruction_pc: u16,
+    pub(crate) cycles: u64,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u8<R: Read>(input: &mut R) -> io::Result<u8> {
+    let mut buffer = [0; 1];
+    input.read_exact(&mut buffer)?;
+    Ok(buffer[0])
+}
+
+fn read_u16<R: Read>(input: &mut R) -> io::Result<u16> {
+    let mut buffer = [0; 2];
+    input.read_exact(&mut buffer)?;
+    Ok(u16::from_le_bytes(buffer))
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut buffer = [0; 4];
+    input.read_exact(&mut buffer)?;
+    Ok(u32::from_le_bytes(buffer))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut buffer = [0; 8];
+    input.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+fn read_bool<R: Read>(input: &mut R) -> io::Result<bool> {
+    match read_u8(input)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(invalid("invalid boolean")),
+    }
+}
+
+impl Snapshot {
+    /// Writes the snapshot in little endian, fields in declaration order.
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        output.write_all(&(self.i as u32).to_le_bytes())?;
+        output.write_all(&self.pc.to_le_bytes())?;
+        output.write_all(&[self.sp as u8])?;
+        output.write_all(&self.v)?;
+        output.write_all(&[self.dt, self.st])?;
+        for entry in self.stack.iter() {
+            output.write_all(&entry.to_le_bytes())?;
+        }
+        output.write_all(&self.ram)?;
+        let resolution = match self.vram.resolution() {
+            Resolution::Low => 0,
+            Resolution::High => 1,
+        };
+        output.write_all(&[resolution])?;
+        for line in self.vram.lines() {
+            output.write_all(line)?;
+        }
+        output.write_all(&self.flags)?;
+        output.write_all(&[self.halted as u8, self.plane])?;
+        output.write_all(&self.pattern)?;
+        output.write_all(&[self.pitch, self.vblank as u8])?;
+        output.write_all(&self.random.to_le_bytes())?;
+        output.write_all(&self.instruction_pc.to_le_bytes())?;
+        output.write_all(&self.cycles.to_le_bytes())
+    }
+
+    pub fn read<R: Read>(input: &mut R) -> io::Result<Self> {
+        let i = read_u32(input)? as usize;
+        let pc = read_u16(input)?;
+        let sp = read_u8(input)? as usize;
+        if sp > STACK_SIZE {
+            return Err(invalid("stack pointer out of range"));
+        }
+        let mut v = [0; GENERAL_PURPOSE_REGISTERS];
+        input.read_exact(&mut v)?;
+        let dt = read_u8(input)?;
+        let st = read_u8(input)?;
+        let mut stack = [0; STACK_SIZE];
+        for entry in stack.iter_mut() {
+            *entry = read_u16(input)?;
+        }
+        let mut ram = vec![0; MEMORY_SIZE];
+        input.read_exact(&mut ram)?;
+        let mut vram = match read_u8(input)? {
+            0 => VRAM::new(Resolution::Low),
+            1 => VRAM::new(Resolution::High),
+            _ => return Err(invalid("invalid resolution")),
+        };
+        for y in 0..vram.height() {
+            let mut line = vec![0; vram.width()];
+            input.read_exact(&mut line)?;
+            for (x, &pixel) in line.iter().enumerate() {
+                vram.set(x, y, pixel);
+            }
+        }
+        let mut flags = [0; FLAG_REGISTERS];
+        input.read_exact(&mut flags)?;
+        let halted = read_bool(input)?;
+        let plane = read_u8(input)?;
+        let mut pattern = [0; PATTERN_SIZE];
+        input.read_exact(&mut pattern)?;
+        let pitch = read_u8(input)?;
+        let vblank = read_bool(input)?;
+        let random = read_u64(input)?;
+        let instruction_pc = read_u16(input)?;
+        let cycles = read_u64(input)?;
+
+        Ok(Self {
+            i,
+            pc,
+            sp,
+            v,
+            dt,
+            st,
+            stack,
+            ram,
+            vram,
+            flags,
+            halted,
+            plane,
+            pattern,
+            pitch,
+            vblank,
+            random,
+            instruction_pc,
+            cycles,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        let snapshot = Self::read(&mut bytes)?;
+
+        if !bytes.is_empty() {
+            return Err(invalid("trailing data after snapshot"));
+        }
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod snapshot_test {
+    use super::*;
+    use crate::cpu::CPU;
+    use crate::keypad::State;
+
+    fn running_cpu() -> CPU {
+        // HIGH; LD V1, 0x2a; RND V2, 0xff; CALL 0x200
+        let mut cpu = CPU::init(&[0x00, 0xFF, 0x61, 0x2A, 0xC2, 0xFF, 0x22, 0x00]);
+        cpu.set_seed(42);
+        cpu.run_frame(&State::new(), 5).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let snapshot = running_cpu().snapshot();
+
+        let bytes = snapshot.to_bytes();
+        assert_eq!(Snapshot::from_bytes(&bytes).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = running_cpu().snapshot().to_bytes();
+
+        assert!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Snapshot::from_bytes(&[bytes.clone(), vec![0]].concat()).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+/// Frames run per host frame while fast-forwarding, by default.
+pub const DEFAULT_FAST_FORWARD: usize = 4;
+/// Slow motion divisors, cycled through: full, half and quarter speed.
+pub const SLOW_MOTION: [u64; 3] = [1, 2, 4];
+
+/// Pause, frame advance, fast-forward and slow motion, as the number of
+/// emulated frames run during each host frame.
+pub struct Speed {
+    paused: bool,
+    advance: bool,
+    fast_forward: usize,
+    fast_forwarding: bool,
+    slow_motion: usize,
+    host_frames: u64,
+}
+
+impl Speed {
+    /// `fast_forward` frames are run per host frame while fast-forwarding.
+    pub fn new(fast_forward: usize) -> Self {
+        Self {
+            paused: false,
+            advance: false,
+            fast_forward,
+            fast_forwarding: false,
+            slow_motion: 0,
+            host_frames: 0,
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.advance = false
+    }
+
+    /// Runs a single frame on the next host frame, when paused.
+    pub fn advance(&mut self) {
+        self.advance = self.paused
+    }
+
+    pub fn set_fast_forwarding(&mut self, fast_forwarding: bool) {
+        self.fast_forwarding = fast_forwarding
+    }
+
+    /// Divisor of the current speed: 1 at full speed, 2 at half speed...
+    pub fn slow_motion(&self) -> u64 {
+        SLOW_MOTION[self.slow_motion]
+    }
+
+    /// Moves on to the next slow motion speed, returning its divisor.
+    pub fn next_slow_motion(&mut self) -> u64 {
+        self.slow_motion = (self.slow_motion + 1) % SLOW_MOTION.len();
+        self.slow_motion()
+    }
+
+    /// Number of emulated frames to run during the next host frame.
+    ///
+    /// Fast-forwarding overrides slow motion, and neither applies while
+    /// paused.
+    pub fn frames(&mut self) -> usize {
+        self.host_frames += 1;
+
+        if self.paused {
+            let advance = self.advance;
+            self.advance = false;
+            return advance as usize;
+        }
+        if self.fast_forwarding {
+            return self.fast_forward;
+        }
+
+        (self.host_frames % self.slow_motion() == 0) as usize
+    }
+}
+
+#[cfg(test)]
+mod speed_test {
+    use super::*;
+
+    fn run(speed: &mut Speed, host_frames: usize) -> usize {
+        (0..host_frames).map(|_| speed.frames()).sum()
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut speed = Speed::new(DEFAULT_FAST_FORWARD);
+        assert_eq!(run(&mut speed, 3), 3);
+
+        speed.toggle_pause();
+        assert_eq!(run(&mut speed, 3), 0);
+        speed.advance();
+        assert_eq!(run(&mut speed, 3), 1);
+
+        speed.toggle_pause();
+        speed.advance();
+        assert_eq!(run(&mut speed, 3), 3);
+    }
+
+    #[test]
+    fn test_fast_forward_and_slow_motion() {
+        let mut speed = Speed::new(3);
+
+        assert_eq!(speed.next_slow_motion(), 2);
+        assert_eq!(run(&mut speed, 8), 4);
+        assert_eq!(speed.next_slow_motion(), 4);
+        assert_eq!(run(&mut speed, 8), 2);
+
+        speed.set_fast_forwarding(true);
+        assert_eq!(run(&mut speed, 2), 6);
+        speed.set_fast_forwarding(false);
+
+        assert_eq!(speed.next_slow_motion(), 1);
+        assert_eq!(run(&mut speed, 5), 5);
+    }
+}
@@ -0,0 +1,143 @@
+extern crate sdl2;
+
+use sdl2::rect::Rect;
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+
+/// Box drawn for the characters without a glyph.
+const MISSING: [u8; GLYPH_HEIGHT] = [0b111, 0b101, 0b101, 0b101, 0b111];
+
+/// Rows of a glyph, 3 pixels wide with the leftmost in the highest bit.
+/// Letters are drawn in uppercase; only ASCII has glyphs.
+pub fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    Some(match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b111, 0b001, 0b011, 0b000, 0b010],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '&' => [0b010, 0b101, 0b010, 0b101, 0b011],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '`' => [0b100, 0b010, 0b000, 0b000, 0b000],
+        '^' => [0b010, 0b101, 0b000, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '|' => [0b010, 0b010, 0b010, 0b010, 0b010],
+        _ => return None,
+    })
+}
+
+/// Whether every character of `text` has a glyph.
+pub fn is_drawable(text: &str) -> bool {
+    text.chars().all(|c| glyph(c).is_some())
+}
+
+/// Rectangles drawing text, sorted by ink for each ink to be filled at once.
+pub struct Page {
+    scale: usize,
+    rects: Vec<Vec<Rect>>,
+}
+
+impl Page {
+    /// Page of text in `inks` colors, glyph pixels being `scale` wide.
+    pub fn new(scale: usize, inks: usize) -> Self {
+        Self {
+            scale,
+            rects: vec![Vec::new(); inks],
+        }
+    }
+
+    /// Size of a character cell, glyph and spacing, in pixels.
+    pub fn cell(&self) -> (usize, usize) {
+        (
+            (GLYPH_WIDTH + 1) * self.scale,
+            (GLYPH_HEIGHT + 2) * self.scale,
+        )
+    }
+
+    /// Writes `text` with its top left corner at `x`, `y`.
+    pub fn text_at(&mut self, x: usize, y: usize, text: &str, ink: usize) {
+        let (width, _) = self.cell();
+        let scale = self.scale;
+
+        for (index, c) in text.chars().enumerate() {
+            let x = x + index * width;
+
+            for (line, bits) in glyph(c).unwrap_or(MISSING).iter().enumerate() {
+                for bit in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> bit) != 0 {
+                        self.rects[ink].push(Rect::new(
+                            (x + bit * scale) as i32,
+                            (y + line * scale) as i32,
+                            scale as u32,
+                            scale as u32,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes `text` in the character cell at `column`, `row`.
+    pub fn text(&mut self, column: usize, row: usize, text: &str, ink: usize) {
+        let (width, height) = self.cell();
+
+        self.text_at(column * width, row * height + self.scale, text, ink)
+    }
+
+    pub fn rects(&self, ink: usize) -> &[Rect] {
+        &self.rects[ink]
+    }
+}
@@ -1,107 +1,240 @@
-use std::time;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+
+// Slices a tracker may fall behind before it drops them instead of catching up.
+const MAX_LAG: u64 = 4;
+
+/// Splits time into fixed slices at a given frequency.
+///
+/// Slice deadlines are computed from the start of the tracker, in
+/// nanoseconds, so that neither a late `reset()` nor rounding accumulates
+/// into drift.
+pub struct FrequencyTracker<C: Clock = SystemClock> {
+    clock: C,
+    frequency: f64,
+    start: Duration,
+    slices: u64,
+    dropped: u64,
+}
 
-pub struct FrequencyTracker {
-    slice: u64,
-    counter: time::Instant,
+impl FrequencyTracker<SystemClock> {
+    pub fn new(frequency: f64) -> Self {
+        Self::with_clock(frequency, SystemClock::new())
+    }
 }
 
-impl FrequencyTracker {
-    pub fn new(frequency: u64) -> Self {
+impl<C: Clock> FrequencyTracker<C> {
+    pub fn with_clock(frequency: f64, clock: C) -> Self {
+        assert!(frequency > 0.0, "frequency must be positive");
+
         Self {
-            slice: time::Duration::from_millis(1000 / frequency).as_millis() as u64,
-            counter: time::Instant::now(),
+            frequency,
+            start: clock.now(),
+            clock,
+            slices: 0,
+            dropped: 0,
         }
     }
 
-    pub fn from_str(frequency: &str) -> Result<Self, String> {
-        if let Ok(value) = frequency.to_string().parse::<u64>() {
-            Ok(Self::new(value))
-        } else {
-            Err(format!(
-                "Couldn't parse value '{}' as a frequency.",
-                frequency
-            ))
-        }
+    pub fn clock(&self) -> &C {
+        &self.clock
     }
 
-    pub fn burnt_duration(&self) -> i128 {
-        let result = self.slice as i128 - self.counter.elapsed().as_millis() as i128;
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
 
-        if result < 0 {
-            -result
-        } else {
-            0
-        }
+    pub fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.frequency)
+    }
+
+    /// End of the current slice, in the time of the clock.
+    pub fn deadline(&self) -> Duration {
+        self.start + Duration::from_secs_f64((self.slices + 1) as f64 / self.frequency)
+    }
+
+    /// How late the current slice is, zero while it is still running.
+    pub fn burnt_duration(&self) -> Duration {
+        self.clock
+            .now()
+            .checked_sub(self.deadline())
+            .unwrap_or_default()
     }
 
     pub fn is_burnt(&self) -> bool {
-        self.burnt_duration() > 0
+        self.clock.now() >= self.deadline()
     }
 
-    pub fn reset(&mut self) {
-        self.counter = time::Instant::now()
+    /// Time left before the current slice is burnt.
+    pub fn remaining(&self) -> Duration {
+        self.deadline()
+            .checked_sub(self.clock.now())
+            .unwrap_or_default()
+    }
+
+    /// Sleeps on the clock until the current slice is burnt.
+    pub fn wait(&mut self) {
+        let remaining = self.remaining();
+
+        if remaining > Duration::from_secs(0) {
+            self.clock.sleep(remaining)
+        }
+    }
+
+    /// Moves on to the next slice.
+    ///
+    /// A late slice shortens the following ones until the schedule is caught
+    /// up. When more than a few slices behind, the missed slices are dropped
+    /// instead; their number is returned and added to `dropped()`.
+    pub fn reset(&mut self) -> u64 {
+        self.slices += 1;
+
+        let behind = (self.burnt_duration().as_secs_f64() * self.frequency) as u64;
+        if behind <= MAX_LAG {
+            return 0;
+        }
+
+        self.slices += behind;
+        self.dropped += behind;
+
+        behind
+    }
+
+    /// Number of slices dropped since the tracker was created.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl FromStr for FrequencyTracker {
+    type Err = String;
+
+    fn from_str(frequency: &str) -> Result<Self, Self::Err> {
+        match frequency.parse::<f64>() {
+            Ok(value) if value > 0.0 && value.is_finite() => Ok(Self::new(value)),
+            _ => Err(format!(
+                "Couldn't parse value '{}' as a frequency.",
+                frequency
+            )),
+        }
     }
 }
 
 #[cfg(test)]
 mod frequency_tracker_test {
     use super::*;
-    use std::thread;
+    use crate::clock::VirtualClock;
+
+    fn virtual_tracker(frequency: f64) -> FrequencyTracker<VirtualClock> {
+        FrequencyTracker::with_clock(frequency, VirtualClock::new())
+    }
 
     #[test]
     fn new_ft_test() {
-        let ft = FrequencyTracker::new(500);
-
-        assert_eq!(ft.slice, 2);
+        let ft = FrequencyTracker::new(500.0);
+        assert_eq!(ft.period(), Duration::from_millis(2));
 
-        let ft = FrequencyTracker::new(60);
+        let ft = FrequencyTracker::new(60.0);
+        assert_eq!(ft.period(), Duration::from_nanos(16_666_667));
 
-        assert_eq!(ft.slice, 16);
+        let ft = FrequencyTracker::new(2000.0);
+        assert_eq!(ft.period(), Duration::from_micros(500));
     }
 
     #[test]
     fn new_ft_from_str_test() {
-        let ft = FrequencyTracker::from_str("500").unwrap();
-        assert_eq!(ft.slice, 2);
+        let ft = "59.94".parse::<FrequencyTracker>().unwrap();
+        assert_eq!(ft.frequency(), 59.94);
 
-        let ft = FrequencyTracker::from_str("60").unwrap();
-        assert_eq!(ft.slice, 16);
+        assert!("a".parse::<FrequencyTracker>().is_err());
+        assert!("0".parse::<FrequencyTracker>().is_err());
+        assert!("-60".parse::<FrequencyTracker>().is_err());
+        assert!("inf".parse::<FrequencyTracker>().is_err());
+    }
 
-        let ft = FrequencyTracker::from_str("a");
-        assert!(ft.is_err());
+    #[test]
+    fn deadline_test() {
+        let mut ft = virtual_tracker(60.0);
+
+        for _ in 0..60 {
+            ft.wait();
+            assert_eq!(ft.reset(), 0);
+        }
+        assert_eq!(ft.clock().now(), Duration::from_secs(1));
+        assert_eq!(ft.deadline(), Duration::from_secs(1) + ft.period());
     }
 
     #[test]
     fn burnt_duration() {
-        let ft = FrequencyTracker::new(500);
+        let mut ft = virtual_tracker(500.0);
 
-        thread::sleep(time::Duration::from_millis(3));
+        ft.clock_mut().advance(Duration::from_millis(3));
+        assert_eq!(ft.burnt_duration(), Duration::from_millis(1));
 
-        assert!(ft.burnt_duration() > 0);
-
-        let ft = FrequencyTracker::new(60);
-        assert!(ft.burnt_duration() == 0);
+        let ft = virtual_tracker(60.0);
+        assert_eq!(ft.burnt_duration(), Duration::from_millis(0));
     }
 
     #[test]
     fn is_burnt_test() {
-        let ft = FrequencyTracker::new(500);
+        let mut ft = virtual_tracker(500.0);
 
-        thread::sleep(time::Duration::from_millis(3));
+        ft.clock_mut().advance(Duration::from_micros(1999));
+        assert!(!ft.is_burnt());
 
+        ft.clock_mut().advance(Duration::from_micros(1));
         assert!(ft.is_burnt());
+    }
+
+    #[test]
+    fn remaining_test() {
+        let mut ft = virtual_tracker(60.0);
 
-        let ft = FrequencyTracker::new(60);
+        assert_eq!(ft.remaining(), ft.period());
+        ft.clock_mut().advance(Duration::from_millis(10));
+        assert_eq!(ft.remaining(), ft.period() - Duration::from_millis(10));
+    }
+
+    #[test]
+    fn wait_test() {
+        let mut ft = virtual_tracker(60.0);
+
+        ft.wait();
+        assert!(ft.is_burnt());
+        assert_eq!(ft.clock().now(), ft.period());
+    }
+
+    #[test]
+    fn reset_catch_up_test() {
+        let mut ft = virtual_tracker(500.0);
+
+        ft.clock_mut().advance(Duration::from_millis(5));
+        assert_eq!(ft.reset(), 0);
+        assert_eq!(ft.slices, 1);
+        assert!(ft.is_burnt());
+
+        assert_eq!(ft.reset(), 0);
         assert!(!ft.is_burnt());
+        assert_eq!(ft.remaining(), Duration::from_millis(1));
     }
 
     #[test]
-    fn reset_test() {
-        let mut ft = FrequencyTracker::new(60);
+    fn reset_dropped_test() {
+        let mut ft = virtual_tracker(1000.0);
 
-        thread::sleep(time::Duration::from_millis(10));
-        assert!(ft.counter.elapsed().as_millis() >= 10);
-        ft.reset();
-        assert!(ft.counter.elapsed().as_millis() < 10);
+        ft.clock_mut().advance(Duration::from_micros(20_500));
+        assert_eq!(ft.reset(), 18);
+        assert_eq!(ft.dropped(), 18);
+        assert!(ft.is_burnt());
+
+        assert_eq!(ft.reset(), 0);
+        assert!(!ft.is_burnt());
+        assert!(ft.remaining() <= Duration::from_micros(500));
     }
 }
@@ -0,0 +1,304 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::cpu::CPU;
+use crate::decoder::{Instruction, OpcodeClass};
+use crate::hash::fnv1a;
+
+/// Formats the trace line of `instruction`, about to run on `cpu`:
+///
+/// ```text
+/// 0000000042 0204 d015 v=0a0b0000000000000000000000000000 i=0050 sp=00 dt=00 st=00 DRW V0, V1, 0x5
+/// ```
+///
+/// The cycle on 10 decimal digits, then in hexadecimal PC, the opcode, V0 to
+/// VF, I, SP, DT and ST, the optional `mem=` hash of the RAM, and the
+/// disassembled instruction. The format only changes with a new major version.
+pub fn format_line(cpu: &CPU, instruction: &Instruction, memory_hash: bool) -> String {
+    let registers: String = cpu.v().iter().map(|v| format!("{:02x}", v)).collect();
+    let memory = if memory_hash {
+        format!(" mem={:016x}", fnv1a(cpu.ram().iter()))
+    } else {
+        String::new()
+    };
+
+    format!(
+        "{:010} {:04x} {:04x} v={} i={:04x} sp={:02x} dt={:02x} st={:02x}{} {}",
+        cpu.cycles(),
+        cpu.pc(),
+        instruction.raw,
+        registers,
+        cpu.i(),
+        cpu.sp(),
+        cpu.dt(),
+        cpu.st(),
+        memory,
+        instruction
+    )
+}
+
+/// Parses an inclusive range of hexadecimal addresses, as `200-2ff`.
+pub fn parse_pc_range(range: &str) -> Result<RangeInclusive<u16>, String> {
+    let error = || format!("Couldn't parse value '{}' as an address range.", range);
+    let mut bounds = range.splitn(2, '-');
+    let start = bounds.next().ok_or_else(error)?;
+    let end = bounds.next().ok_or_else(error)?;
+
+    let start = u16::from_str_radix(start.trim_start_matches("0x"), 16).map_err(|_| error())?;
+    let end = u16::from_str_radix(end.trim_start_matches("0x"), 16).map_err(|_| error())?;
+    if start > end {
+        return Err(error());
+    }
+
+    Ok(start..=end)
+}
+
+/// Instructions written to a trace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceFilter {
+    /// Only instructions at these addresses, when set.
+    pub pc_range: Option<RangeInclusive<u16>>,
+    /// Only instructions of these classes, when not empty.
+    pub classes: Vec<OpcodeClass>,
+}
+
+impl TraceFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn matches(&self, pc: u16, instruction: &Instruction) -> bool {
+        let in_range = match self.pc_range {
+            Some(ref range) => range.contains(&pc),
+            None => true,
+        };
+
+        in_range && (self.classes.is_empty() || self.classes.contains(&instruction.opcode.class()))
+    }
+}
+
+/// Trace options as given on the command line, shared by the frontends.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceOptions<'a> {
+    /// File to trace to, no trace when unset.
+    pub path: Option<&'a str>,
+    /// Range of addresses to trace, as `200-2ff`.
+    pub pc_range: Option<&'a str>,
+    /// Names of the opcode classes to trace, all when empty.
+    pub classes: Vec<&'a str>,
+    pub memory_hash: bool,
+}
+
+impl<'a> TraceOptions<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(&self) -> Result<TraceFilter, String> {
+        let mut filter = TraceFilter::new();
+        if let Some(range) = self.pc_range {
+            filter.pc_range = Some(parse_pc_range(range)?);
+        }
+        filter.classes = self
+            .classes
+            .iter()
+            .map(|class| class.parse())
+            .collect::<Result<_, String>>()?;
+
+        Ok(filter)
+    }
+
+    /// Creates the trace file, if any.
+    pub fn create(&self) -> Result<Option<Trace>, String> {
+        let path = match self.path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let mut trace = Trace::create(Path::new(path), self.filter()?)
+            .map_err(|error| format!("{}: {}", path, error))?;
+        trace.set_memory_hash(self.memory_hash);
+
+        Ok(Some(trace))
+    }
+}
+
+/// Writes the trace lines of the instructions passing a filter. An
+/// instruction waiting for a key or the next frame is only traced on the
+/// cycle it runs.
+///
+/// Write errors stop the trace; the first one is returned by `finish()`.
+pub struct Trace {
+    output: Box<dyn Write>,
+    filter: TraceFilter,
+    memory_hash: bool,
+    error: Option<io::Error>,
+}
+
+impl Trace {
+    pub fn new(output: Box<dyn Write>, filter: TraceFilter) -> Self {
+        Self {
+            output,
+            filter,
+            memory_hash: false,
+            error: None,
+        }
+    }
+
+    /// Traces to a new file at `path`.
+    pub fn create(path: &Path, filter: TraceFilter) -> io::Result<Self> {
+        let file = File::create(path)?;
+
+        Ok(Self::new(Box::new(BufWriter::new(file)), filter))
+    }
+
+    /// Adds a hash of the RAM to every line. Slow, but lets traces be
+    /// compared on memory too.
+    pub fn set_memory_hash(&mut self, memory_hash: bool) {
+        self.memory_hash = memory_hash
+    }
+
+    /// Records `instruction`, about to run on `cpu`.
+    pub fn record(&mut self, cpu: &CPU, instruction: &Instruction) {
+        if self.error.is_some() || !self.filter.matches(cpu.pc(), instruction) {
+            return;
+        }
+
+        if let Err(error) = writeln!(
+            self.output,
+            "{}",
+            format_line(cpu, instruction, self.memory_hash)
+        ) {
+            self.error = Some(error);
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.output.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod trace_test {
+    use super::*;
+    use crate::keypad::State;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Writer whose contents stay readable once boxed.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn traced_lines(filter: TraceFilter) -> Vec<String> {
+        // LD VA, 0x02; LD I, 0x050; DRW VA, VA, 0x5; JP 0x200
+        let mut cpu = CPU::init(&[0x6A, 0x02, 0xA0, 0x50, 0xDA, 0xA5, 0x12, 0x00]);
+        let output = Shared::default();
+        cpu.set_trace(Some(Trace::new(Box::new(output.clone()), filter)));
+
+        cpu.run_frame(&State::new(), 5).unwrap();
+        cpu.take_trace().unwrap().finish().unwrap();
+
+        let text = String::from_utf8(output.0.borrow().clone()).unwrap();
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_format() {
+        let lines = traced_lines(TraceFilter::new());
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "0000000000 0200 6a02 v=00000000000000000000000000000000 i=0000 sp=00 dt=00 st=00 LD VA, 0x02"
+        );
+        assert_eq!(
+            lines[2],
+            "0000000002 0204 daa5 v=00000000000000000000020000000000 i=0050 sp=00 dt=00 st=00 DRW VA, VA, 0x5"
+        );
+        assert!(lines[4].starts_with("0000000004 0200 6a02 "));
+    }
+
+    #[test]
+    fn test_wait_traced_once() {
+        // LD V0, K; JP 0x202
+        let mut cpu = CPU::init(&[0xF0, 0x0A, 0x12, 0x02]);
+        let output = Shared::default();
+        cpu.set_trace(Some(Trace::new(
+            Box::new(output.clone()),
+            TraceFilter::new(),
+        )));
+
+        cpu.run_frame(&State::new(), 3).unwrap();
+        cpu.run_frame(&State::from_raw(1), 2).unwrap();
+        cpu.take_trace().unwrap().finish().unwrap();
+
+        let text = String::from_utf8(output.0.borrow().clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("0000000003 0200 f00a "));
+        assert!(lines[1].starts_with("0000000004 0202 1202 "));
+    }
+
+    #[test]
+    fn test_filter() {
+        let lines = traced_lines(TraceFilter {
+            pc_range: Some(0x202..=0x206),
+            classes: vec![OpcodeClass::Memory, OpcodeClass::Flow],
+        });
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("LD I, 0x050"));
+        assert!(lines[1].ends_with("JP 0x200"));
+    }
+
+    #[test]
+    fn test_memory_hash() {
+        let cpu = CPU::new();
+        let line = format_line(&cpu, &Instruction::decode(0x00E0), true);
+
+        assert!(line.ends_with(&format!(" st=00 mem={:016x} CLS", fnv1a(cpu.ram().iter()))));
+    }
+
+    #[test]
+    fn test_options() {
+        let mut options = TraceOptions::new();
+        assert!(options.create().unwrap().is_none());
+
+        options.pc_range = Some("200-2ff");
+        options.classes = vec!["memory", "flow"];
+        assert_eq!(
+            options.filter(),
+            Ok(TraceFilter {
+                pc_range: Some(0x200..=0x2ff),
+                classes: vec![OpcodeClass::Memory, OpcodeClass::Flow],
+            })
+        );
+
+        options.classes = vec!["nope"];
+        assert!(options.filter().is_err());
+    }
+
+    #[test]
+    fn test_parse_pc_range() {
+        assert_eq!(parse_pc_range("200-2ff"), Ok(0x200..=0x2ff));
+        assert_eq!(parse_pc_range("0x200-0x210"), Ok(0x200..=0x210));
+        assert!(parse_pc_range("300-200").is_err());
+        assert!(parse_pc_range("200").is_err());
+    }
+}
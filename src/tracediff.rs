@@ -0,0 +1,214 @@
+use std::str::FromStr;
+
+/// A parsed line of a trace in the format of `crate::trace`.
+///
+/// Other emulators' traces are accepted as long as they start with the
+/// decimal cycle, PC and opcode, followed by `key=value` fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceLine {
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub fields: Vec<(String, String)>,
+    pub mnemonic: String,
+}
+
+impl FromStr for TraceLine {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut tokens = line.split(' ').filter(|token| !token.is_empty()).peekable();
+        let mut next = |name: &str| {
+            tokens
+                .next()
+                .ok_or_else(|| format!("missing {} in '{}'", name, line))
+        };
+
+        let cycle = next("cycle")?;
+        let cycle = cycle
+            .parse::<u64>()
+            .map_err(|_| format!("Couldn't parse value '{}' as a cycle.", cycle))?;
+        let pc = next("PC")?;
+        let pc = u16::from_str_radix(pc, 16)
+            .map_err(|_| format!("Couldn't parse value '{}' as an address.", pc))?;
+        let opcode = next("opcode")?;
+        let opcode = u16::from_str_radix(opcode, 16)
+            .map_err(|_| format!("Couldn't parse value '{}' as an opcode.", opcode))?;
+
+        let mut fields = Vec::new();
+        while let Some(field) = tokens.peek() {
+            let mut parts = field.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => fields.push((key.to_string(), value.to_string())),
+                _ => break,
+            }
+            tokens.next();
+        }
+        let mnemonic = tokens.collect::<Vec<&str>>().join(" ");
+
+        Ok(Self {
+            cycle,
+            pc,
+            opcode,
+            fields,
+            mnemonic,
+        })
+    }
+}
+
+impl TraceLine {
+    fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Names of the parts of the state differing between two lines, comparing
+/// only the fields present in both. The mnemonics are not compared, their
+/// syntax varying between disassemblers.
+pub fn differences(a: &TraceLine, b: &TraceLine) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    if a.pc != b.pc {
+        differences.push("PC".to_string());
+    }
+    if a.opcode != b.opcode {
+        differences.push("opcode".to_string());
+    }
+
+    for (key, value) in a.fields.iter() {
+        let other = match b.field(key) {
+            Some(other) if other != value => other,
+            _ => continue,
+        };
+
+        match key.as_str() {
+            // Registers are compared one by one to name the culprit.
+            "v" if value.len() == other.len() && value.len() % 2 == 0 => {
+                for register in 0..value.len() / 2 {
+                    let range = register * 2..register * 2 + 2;
+                    if value[range.clone()] != other[range] {
+                        differences.push(format!("V{:X}", register));
+                    }
+                }
+            }
+            "mem" => differences.push("memory".to_string()),
+            _ => differences.push(key.to_uppercase()),
+        }
+    }
+
+    differences
+}
+
+/// First point where two traces disagree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// Indices of the diverging lines, `None` for a trace that ended.
+    pub a: Option<usize>,
+    pub b: Option<usize>,
+    pub differences: Vec<String>,
+}
+
+/// Lines up two traces by cycle and finds the first cycle whose state
+/// differs. Cycles missing from one of the traces, as left out by a trace
+/// filter, are skipped.
+pub fn first_divergence(a: &[TraceLine], b: &[TraceLine]) -> Option<Divergence> {
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i].cycle < b[j].cycle {
+            i += 1;
+        } else if a[i].cycle > b[j].cycle {
+            j += 1;
+        } else {
+            let differences = differences(&a[i], &b[j]);
+            if !differences.is_empty() {
+                return Some(Divergence {
+                    a: Some(i),
+                    b: Some(j),
+                    differences,
+                });
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+
+    if i == a.len() && j == b.len() {
+        return None;
+    }
+
+    Some(Divergence {
+        a: if i < a.len() { Some(i) } else { None },
+        b: if j < b.len() { Some(j) } else { None },
+        differences: vec!["length".to_string()],
+    })
+}
+
+#[cfg(test)]
+mod tracediff_test {
+    use super::*;
+
+    const LINE: &str =
+        "0000000042 0204 d015 v=0a0b0000000000000000000000000000 i=0050 sp=00 dt=00 st=00 DRW V0, V1, 0x5";
+
+    fn lines(text: &str) -> Vec<TraceLine> {
+        text.lines().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let line: TraceLine = LINE.parse().unwrap();
+
+        assert_eq!(line.cycle, 42);
+        assert_eq!(line.pc, 0x204);
+        assert_eq!(line.opcode, 0xd015);
+        assert_eq!(line.fields.len(), 5);
+        assert_eq!(line.field("i"), Some("0050"));
+        assert_eq!(line.mnemonic, "DRW V0, V1, 0x5");
+
+        assert!("42 020g d015".parse::<TraceLine>().is_err());
+        assert!("42 0200".parse::<TraceLine>().is_err());
+    }
+
+    #[test]
+    fn test_differences() {
+        let a: TraceLine = LINE.parse().unwrap();
+        let b: TraceLine = LINE
+            .replace("v=0a0b", "v=0a0c")
+            .replace("i=0050", "i=0051")
+            .parse()
+            .unwrap();
+
+        assert_eq!(differences(&a, &a), Vec::<String>::new());
+        assert_eq!(differences(&a, &b), vec!["V1", "I"]);
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let a = lines("0 0200 6001 i=0000\n1 0202 6102 i=0000\n2 0204 a300 i=0000");
+        let b = lines("0 0200 6001 i=0000\n2 0204 a300 i=0001");
+
+        assert_eq!(
+            first_divergence(&a, &b),
+            Some(Divergence {
+                a: Some(2),
+                b: Some(1),
+                differences: vec!["I".to_string()],
+            })
+        );
+        assert_eq!(first_divergence(&a, &a), None);
+    }
+
+    #[test]
+    fn test_length_divergence() {
+        let a = lines("0 0200 6001\n1 0202 6102");
+        let b = lines("0 0200 6001");
+
+        let divergence = first_divergence(&a, &b).unwrap();
+        assert_eq!((divergence.a, divergence.b), (Some(1), None));
+        assert_eq!(divergence.differences, vec!["length"]);
+    }
+}
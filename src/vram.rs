@@ -0,0 +1,238 @@
+use crate::hash::fnv1a;
+use crate::{HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    /// 64x32, the original CHIP-8 display.
+    Low,
+    /// 128x64, the SUPER-CHIP extended display.
+    High,
+}
+
+impl Resolution {
+    pub fn width(self) -> usize {
+        match self {
+            Resolution::Low => WIDTH,
+            Resolution::High => HIRES_WIDTH,
+        }
+    }
+
+    pub fn height(self) -> usize {
+        match self {
+            Resolution::Low => HEIGHT,
+            Resolution::High => HIRES_HEIGHT,
+        }
+    }
+}
+
+/// Framebuffer whose dimensions follow the selected resolution.
+///
+/// Each pixel holds one bit per bitplane, giving four colours with the two
+/// XO-CHIP planes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VRAM {
+    resolution: Resolution,
+    pixels: Vec<u8>,
+}
+
+impl VRAM {
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            pixels: vec![0; resolution.width() * resolution.height()],
+        }
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Switches to `resolution`, clearing the screen.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        *self = Self::new(resolution)
+    }
+
+    pub fn width(&self) -> usize {
+        self.resolution.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.resolution.height()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width() + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, pixel: u8) {
+        let width = self.width();
+        self.pixels[y * width + x] = pixel
+    }
+
+    /// XORs the `planes` bits of a pixel, returning whether one of them was
+    /// lit.
+    pub fn toggle(&mut self, x: usize, y: usize, planes: u8) -> bool {
+        let previous = self.get(x, y);
+        self.set(x, y, previous ^ planes);
+
+        previous & planes != 0
+    }
+
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes
+        }
+    }
+
+    /// FNV-1a hash of the resolution and pixels, stable across builds so
+    /// it can be stored as a golden value.
+    pub fn fingerprint(&self) -> u64 {
+        let size = [self.width() as u8, self.height() as u8];
+
+        fnv1a(size.iter().chain(self.pixels.iter()))
+    }
+
+    pub fn lines(&self) -> std::slice::Chunks<'_, u8> {
+        self.pixels.chunks(self.width())
+    }
+
+    pub fn scroll_down(&mut self, rows: usize, planes: u8) {
+        self.scroll(0, rows as isize, planes)
+    }
+
+    pub fn scroll_right(&mut self, columns: usize, planes: u8) {
+        self.scroll(columns as isize, 0, planes)
+    }
+
+    pub fn scroll_left(&mut self, columns: usize, planes: u8) {
+        self.scroll(-(columns as isize), 0, planes)
+    }
+
+    fn scroll(&mut self, dx: isize, dy: isize, planes: u8) {
+        let width = self.width() as isize;
+        let height = self.height() as isize;
+        let previous = self.pixels.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (fromx, fromy) = (x - dx, y - dy);
+                let moved = if fromx >= 0 && fromx < width && fromy >= 0 && fromy < height {
+                    previous[(fromy * width + fromx) as usize]
+                } else {
+                    0
+                };
+
+                let pixel = &mut self.pixels[(y * width + x) as usize];
+                *pixel = (*pixel & !planes) | (moved & planes);
+            }
+        }
+    }
+}
+
+impl Default for VRAM {
+    fn default() -> Self {
+        Self::new(Resolution::Low)
+    }
+}
+
+#[cfg(test)]
+mod vram_test {
+    use super::*;
+
+    #[test]
+    fn test_new_vram() {
+        let vram = VRAM::new(Resolution::High);
+
+        assert_eq!(vram.width(), HIRES_WIDTH);
+        assert_eq!(vram.height(), HIRES_HEIGHT);
+        assert_eq!(vram.lines().count(), HIRES_HEIGHT);
+        assert!(vram
+            .lines()
+            .all(|line| line.iter().all(|&pixel| pixel == 0)));
+    }
+
+    #[test]
+    fn test_set_resolution() {
+        let mut vram = VRAM::new(Resolution::Low);
+        vram.set(1, 1, 1);
+
+        vram.set_resolution(Resolution::High);
+        assert_eq!(vram.resolution(), Resolution::High);
+        assert_eq!(vram.get(1, 1), 0);
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut vram = VRAM::default();
+
+        assert!(!vram.toggle(3, 2, 1));
+        assert_eq!(vram.get(3, 2), 1);
+        assert!(!vram.toggle(3, 2, 2));
+        assert_eq!(vram.get(3, 2), 3);
+        assert!(vram.toggle(3, 2, 1));
+        assert_eq!(vram.get(3, 2), 2);
+    }
+
+    #[test]
+    fn test_clear_planes() {
+        let mut vram = VRAM::default();
+        vram.set(0, 0, 3);
+
+        vram.clear(1);
+        assert_eq!(vram.get(0, 0), 2);
+        vram.clear(3);
+        assert_eq!(vram.get(0, 0), 0);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut vram = VRAM::default();
+        assert_eq!(vram.fingerprint(), 0x0b8b_5650_919e_108d);
+        assert_ne!(
+            vram.fingerprint(),
+            VRAM::new(Resolution::High).fingerprint()
+        );
+
+        vram.set(1, 0, 1);
+        assert_ne!(vram.fingerprint(), 0x0b8b_5650_919e_108d);
+    }
+
+    #[test]
+    fn test_scroll_down() {
+        let mut vram = VRAM::default();
+        vram.set(5, 0, 1);
+
+        vram.scroll_down(3, 1);
+        assert_eq!(vram.get(5, 0), 0);
+        assert_eq!(vram.get(5, 3), 1);
+    }
+
+    #[test]
+    fn test_scroll_right_left() {
+        let mut vram = VRAM::default();
+        vram.set(0, 1, 1);
+
+        vram.scroll_right(4, 1);
+        assert_eq!(vram.get(0, 1), 0);
+        assert_eq!(vram.get(4, 1), 1);
+
+        vram.scroll_left(4, 1);
+        assert_eq!(vram.get(0, 1), 1);
+        assert_eq!(vram.get(4, 1), 0);
+
+        vram.scroll_left(4, 1);
+        assert!(vram
+            .lines()
+            .all(|line| line.iter().all(|&pixel| pixel == 0)));
+    }
+
+    #[test]
+    fn test_scroll_selected_planes() {
+        let mut vram = VRAM::default();
+        vram.set(0, 0, 3);
+
+        vram.scroll_down(1, 2);
+        assert_eq!(vram.get(0, 0), 1);
+        assert_eq!(vram.get(0, 1), 2);
+    }
+}
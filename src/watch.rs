@@ -0,0 +1,276 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::MEMORY_SIZE;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Fetch of an instruction.
+    Execute,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Execute => "execute",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Watches an inclusive range of addresses for some kinds of accesses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<usize>,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    /// Only report the accesses, without pausing.
+    pub log: bool,
+}
+
+impl Watchpoint {
+    /// Watches writes to `range`.
+    pub fn new(range: RangeInclusive<usize>) -> Self {
+        Self {
+            range,
+            read: false,
+            write: true,
+            execute: false,
+            log: false,
+        }
+    }
+
+    pub fn watches(&self, access: Access, address: usize) -> bool {
+        let kind = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        };
+
+        kind && self.range.contains(&address)
+    }
+
+    /// Sets the watched accesses from letters, as `rw` or `x`.
+    pub fn set_accesses(&mut self, accesses: &str) -> Result<(), String> {
+        if accesses.is_empty() || !accesses.chars().all(|c| "rwx".contains(c)) {
+            return Err(format!(
+                "Couldn't parse value '{}' as accesses, expected letters among r, w and x.",
+                accesses
+            ));
+        }
+
+        self.read = accesses.contains('r');
+        self.write = accesses.contains('w');
+        self.execute = accesses.contains('x');
+        Ok(())
+    }
+}
+
+/// Parses `<addr>[-<end>] [rwx] [log]`, addresses being hexadecimal and
+/// writes being watched by default.
+impl FromStr for Watchpoint {
+    type Err = String;
+
+    fn from_str(watchpoint: &str) -> Result<Self, Self::Err> {
+        let mut words = watchpoint.split_whitespace();
+        let range = words.next().ok_or("Missing address.")?;
+
+        let address = |bound: &str| match usize::from_str_radix(bound.trim_start_matches("0x"), 16)
+        {
+            Ok(address) if address < MEMORY_SIZE => Ok(address),
+            _ => Err(format!("Couldn't parse value '{}' as an address.", bound)),
+        };
+        let mut bounds = range.splitn(2, '-');
+        let start = address(bounds.next().unwrap_or_default())?;
+        let end = match bounds.next() {
+            Some(end) => address(end)?,
+            None => start,
+        };
+        if start > end {
+            return Err(format!(
+                "Couldn't parse value '{}' as an address range.",
+                range
+            ));
+        }
+
+        let mut watchpoint = Self::new(start..=end);
+        for word in words {
+            match word {
+                "log" => watchpoint.log = true,
+                accesses => watchpoint.set_accesses(accesses)?,
+            }
+        }
+
+        Ok(watchpoint)
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:04x}", self.range.start())?;
+        if self.range.start() != self.range.end() {
+            write!(f, "-0x{:04x}", self.range.end())?;
+        }
+
+        let accesses: String = [(self.read, 'r'), (self.write, 'w'), (self.execute, 'x')]
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, letter)| letter)
+            .collect();
+        write!(f, " {}", accesses)?;
+
+        if self.log {
+            write!(f, " log")?;
+        }
+        Ok(())
+    }
+}
+
+/// Access to a watched address.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchHit {
+    pub access: Access,
+    pub address: usize,
+    /// Byte read, written or fetched.
+    pub value: u8,
+    /// Address of the instruction making the access.
+    pub pc: u16,
+    pub log: bool,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} 0x{:04x} = 0x{:02x} at 0x{:04x}",
+            self.access, self.address, self.value, self.pc
+        )
+    }
+}
+
+/// Watchpoints checked by the CPU, and the hits not taken yet.
+#[derive(Clone, Debug, Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    hits: Vec<WatchHit>,
+}
+
+impl Watchpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn add(&mut self, watchpoint: Watchpoint) {
+        self.list.push(watchpoint)
+    }
+
+    /// Removes the watchpoint at `index` in `list()`.
+    pub fn remove(&mut self, index: usize) -> Option<Watchpoint> {
+        if index < self.list.len() {
+            Some(self.list.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Removes the watchpoints equal to `watchpoint`, returning whether
+    /// there was any.
+    pub fn remove_matching(&mut self, watchpoint: &Watchpoint) -> bool {
+        let length = self.list.len();
+        self.list.retain(|other| other != watchpoint);
+
+        self.list.len() != length
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.hits.clear();
+    }
+
+    /// Whether executing at `address` hits a watchpoint which pauses.
+    pub fn breaks_on_execute(&self, address: usize) -> bool {
+        self.list
+            .iter()
+            .any(|watchpoint| !watchpoint.log && watchpoint.watches(Access::Execute, address))
+    }
+
+    /// Records an access, if watched.
+    pub fn check(&mut self, access: Access, address: usize, value: u8, pc: u16) {
+        let mut watching = self
+            .list
+            .iter()
+            .filter(|watchpoint| watchpoint.watches(access, address));
+
+        if let Some(first) = watching.next() {
+            // One pausing watchpoint is enough to pause.
+            let log = first.log && watching.all(|watchpoint| watchpoint.log);
+            self.hits.push(WatchHit {
+                access,
+                address,
+                value,
+                pc,
+                log,
+            });
+        }
+    }
+
+    /// Hits since the last call, in order.
+    pub fn take_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.hits)
+    }
+}
+
+#[cfg(test)]
+mod watch_test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let watchpoint: Watchpoint = "2f0".parse().unwrap();
+        assert_eq!(watchpoint, Watchpoint::new(0x2f0..=0x2f0));
+
+        let watchpoint: Watchpoint = "0x300-0x30f rx log".parse().unwrap();
+        assert_eq!(watchpoint.range, 0x300..=0x30f);
+        assert!(watchpoint.read && !watchpoint.write && watchpoint.execute);
+        assert!(watchpoint.log);
+        assert_eq!(watchpoint.to_string(), "0x0300-0x030f rx log");
+
+        assert!("".parse::<Watchpoint>().is_err());
+        assert!("10000".parse::<Watchpoint>().is_err());
+        assert!("300-200".parse::<Watchpoint>().is_err());
+        assert!("300 q".parse::<Watchpoint>().is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let mut watchpoints = Watchpoints::new();
+        watchpoints.add("300-301 rw log".parse().unwrap());
+        watchpoints.add("301 w".parse().unwrap());
+
+        watchpoints.check(Access::Write, 0x2ff, 1, 0x200);
+        watchpoints.check(Access::Read, 0x300, 2, 0x202);
+        watchpoints.check(Access::Write, 0x301, 3, 0x204);
+        watchpoints.check(Access::Execute, 0x301, 4, 0x301);
+
+        let hits = watchpoints.take_hits();
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].log);
+        assert!(!hits[1].log);
+        assert_eq!(hits[1].to_string(), "write 0x0301 = 0x03 at 0x0204");
+        assert!(watchpoints.take_hits().is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+//! Runs the ROMs listed in `tests/fixtures/golden.txt` headless and compares
+//! a hash of their final screen to the recorded one.
+//!
+//! `CRUST_FIXTURES` points the harness at another fixtures directory, and
+//! `CRUST_SKIP_MISSING` skips the listed ROMs missing from it instead of
+//! failing.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crust::cpu::CPU;
+use crust::headless::{self, Limit};
+use crust::quirks::Profile;
+use crust::rom::load_rom;
+use crust::screenshot::{write_vram, ImageFormat};
+use crust::script::InputScript;
+
+const MANIFEST: &str = "golden.txt";
+
+struct Case {
+    rom: String,
+    profile: Profile,
+    instructions_per_frame: usize,
+    frames: u64,
+    hash: Option<u64>,
+    input: Option<String>,
+}
+
+fn fixtures_dir() -> PathBuf {
+    match env::var_os("CRUST_FIXTURES") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+    }
+}
+
+fn parse_case(line: &str) -> Result<Case, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 5 && fields.len() != 6 {
+        return Err(format!("expected 5 or 6 fields, got {}", fields.len()));
+    }
+
+    Ok(Case {
+        rom: fields[0].to_string(),
+        profile: fields[1].parse()?,
+        instructions_per_frame: fields[2].parse().map_err(|_| "bad instructions")?,
+        frames: fields[3].parse().map_err(|_| "bad frames")?,
+        hash: match fields[4] {
+            "-" => None,
+            hash => Some(u64::from_str_radix(hash, 16).map_err(|_| "bad hash")?),
+        },
+        input: fields.get(5).map(|input| input.to_string()),
+    })
+}
+
+/// Runs `case`, returning `None` when its ROM is missing.
+fn run_case(dir: &Path, case: &Case) -> Result<Option<CPU>, String> {
+    let rom_path = dir.join(&case.rom);
+    if !rom_path.exists() {
+        return Ok(None);
+    }
+
+    let rom = load_rom(rom_path.to_str().unwrap()).map_err(|error| error.to_string())?;
+    let script = match case.input {
+        Some(ref input) => fs::read_to_string(dir.join(input))
+            .map_err(|error| error.to_string())?
+            .parse::<InputScript>()?,
+        None => InputScript::new(),
+    };
+
+    let mut cpu = CPU::init(&rom);
+    cpu.set_profile(&case.profile)?;
+    cpu.set_seed(0);
+
+    headless::run(
+        &mut cpu,
+        case.instructions_per_frame,
+        Limit::Frames(case.frames),
+        &script,
+    )
+    .map_err(|fault| fault.to_string())?;
+
+    Ok(Some(cpu))
+}
+
+#[test]
+fn test_golden_screens() {
+    let dir = fixtures_dir();
+    let manifest = fs::read_to_string(dir.join(MANIFEST)).unwrap();
+    let mut failures = Vec::new();
+
+    for (number, line) in manifest.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let case = match parse_case(line) {
+            Ok(case) => case,
+            Err(error) => panic!("{}:{}: {}", MANIFEST, number + 1, error),
+        };
+
+        match run_case(&dir, &case) {
+            Ok(None) if env::var_os("CRUST_SKIP_MISSING").is_some() => {
+                eprintln!("skipping {}: not in {}", case.rom, dir.display())
+            }
+            Ok(None) => failures.push(format!(
+                "{}: not in {}, set CRUST_SKIP_MISSING to skip it",
+                case.rom,
+                dir.display()
+            )),
+            Ok(Some(cpu)) => {
+                let hash = cpu.get_vram().fingerprint();
+                let mut screen = Vec::new();
+                write_vram(cpu.get_vram(), ImageFormat::Ascii, &mut screen).unwrap();
+                let screen = String::from_utf8(screen).unwrap();
+
+                match case.hash {
+                    None => eprintln!("{}: not recorded, got {:016x}\n{}", case.rom, hash, screen),
+                    Some(expected) if expected != hash => failures.push(format!(
+                        "{}: expected {:016x}, got {:016x}\n{}",
+                        case.rom, expected, hash, screen
+                    )),
+                    Some(_) => {}
+                }
+            }
+            Err(error) => failures.push(format!("{}: {}", case.rom, error)),
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,54 @@
+; Source of crust-flags.ch8, in the disassembler's syntax.
+;
+; Stores VF after each flag-setting instruction and draws it as a digit. The
+; expected screen is the row 1 1 0 1 1 1 1: carry, no borrow, borrow, SHL and
+; SHR shifting a 1 out, SUBN without borrow, and VF holding the flag rather
+; than the result of SUB VF, V1.
+
+0200: 6b01: LD VB, 0x01
+0202: 6a01: LD VA, 0x01
+0204: 60ff: LD V0, 0xff     ; 0xff + 1 carries
+0206: 6101: LD V1, 0x01
+0208: 8014: ADD V0, V1
+020a: 82f0: LD V2, VF
+020c: 6005: LD V0, 0x05     ; 5 - 1 doesn't borrow
+020e: 8015: SUB V0, V1
+0210: 83f0: LD V3, VF
+0212: 6000: LD V0, 0x00     ; 0 - 1 borrows
+0214: 8015: SUB V0, V1
+0216: 84f0: LD V4, VF
+0218: 6080: LD V0, 0x80     ; SHL shifts a 1 out
+021a: 800e: SHL V0, V0
+021c: 85f0: LD V5, VF
+021e: 6001: LD V0, 0x01     ; SHR shifts a 1 out
+0220: 8006: SHR V0, V0
+0222: 86f0: LD V6, VF
+0224: 6001: LD V0, 0x01     ; 1 - 1 doesn't borrow
+0226: 8017: SUBN V0, V1
+0228: 87f0: LD V7, VF
+022a: 6f05: LD VF, 0x05     ; the flag overwrites the result
+022c: 6103: LD V1, 0x03
+022e: 8f15: SUB VF, V1
+0230: 88f0: LD V8, VF
+0232: f229: LD F, V2
+0234: dab5: DRW VA, VB, 0x5
+0236: 7a05: ADD VA, 0x05
+0238: f329: LD F, V3
+023a: dab5: DRW VA, VB, 0x5
+023c: 7a05: ADD VA, 0x05
+023e: f429: LD F, V4
+0240: dab5: DRW VA, VB, 0x5
+0242: 7a05: ADD VA, 0x05
+0244: f529: LD F, V5
+0246: dab5: DRW VA, VB, 0x5
+0248: 7a05: ADD VA, 0x05
+024a: f629: LD F, V6
+024c: dab5: DRW VA, VB, 0x5
+024e: 7a05: ADD VA, 0x05
+0250: f729: LD F, V7
+0252: dab5: DRW VA, VB, 0x5
+0254: 7a05: ADD VA, 0x05
+0256: f829: LD F, V8
+0258: dab5: DRW VA, VB, 0x5
+025a: 7a05: ADD VA, 0x05
+025c: 125c: JP 0x25c
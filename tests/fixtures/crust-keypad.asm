@@ -0,0 +1,11 @@
+; Source of crust-keypad.ch8, in the disassembler's syntax.
+;
+; Waits for a key with LD V0, K and draws it as a digit. With
+; crust-keypad.keys pressing 7, the expected screen is a single 7.
+
+0200: 6a01: LD VA, 0x01
+0202: 6b01: LD VB, 0x01
+0204: f00a: LD V0, K
+0206: f029: LD F, V0
+0208: dab5: DRW VA, VB, 0x5
+020a: 120a: JP 0x20a